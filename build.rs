@@ -1,5 +1,16 @@
+use std::{env, fs::File, io::Write, path::PathBuf};
+
 fn main() {
+    // 使用项目中的memory.x, 其中为配置存储保留了Flash末尾的页
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    File::create(out.join("memory.x"))
+        .unwrap()
+        .write_all(include_bytes!("memory.x"))
+        .unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
     println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
-}
\ No newline at end of file
+}
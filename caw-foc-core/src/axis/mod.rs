@@ -0,0 +1,274 @@
+//! 驱动器状态机, 决定栅极驱动、PWM输出和当前的控制模式
+
+use crate::{motor::ControlType, protection::TripReason};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AxisState {
+    /// 栅极驱动和PWM关闭
+    Idle,
+    /// 传感器对齐和电流采样零点校准
+    Calibrating,
+    /// 输出已使能, 零电压
+    Armed,
+    /// 按control_type闭环(或开环)控制
+    ClosedLoop,
+    /// 保护跳闸, 输出关闭, 需要ClearFault
+    Fault,
+}
+
+impl AxisState {
+    /// 该状态是否需要使能栅极驱动和PWM
+    pub fn output_enabled(self) -> bool {
+        matches!(
+            self,
+            AxisState::Calibrating | AxisState::Armed | AxisState::ClosedLoop
+        )
+    }
+}
+
+/// 通过CAN/USART请求的状态切换
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AxisRequest {
+    Calibrate,
+    Arm,
+    Run {
+        control_type: ControlType,
+        target: f32,
+    },
+    Disarm,
+    ClearFault,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AxisError {
+    /// 当前状态不接受该请求
+    InvalidRequest {
+        state: AxisState,
+        request: AxisRequest,
+    },
+    /// 未完成传感器对齐, 不能使能输出
+    NotCalibrated,
+    /// Run的目标值为NaN或无穷大
+    InvalidTarget,
+}
+
+/// 上报用的状态快照
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AxisStatus {
+    pub state: AxisState,
+    pub control_type: ControlType,
+    pub fault: Option<TripReason>,
+}
+
+pub struct Axis {
+    state: AxisState,
+    calibrated: bool,
+    control_type: ControlType,
+    target: f32,
+    fault: Option<TripReason>,
+}
+
+impl Axis {
+    /// calibrated为传感器是否已对齐(例如从Flash读取了对齐结果)
+    pub fn new(calibrated: bool) -> Self {
+        Self {
+            state: AxisState::Idle,
+            calibrated,
+            control_type: ControlType::None,
+            target: 0.0,
+            fault: None,
+        }
+    }
+
+    /// 处理外部请求, 返回切换后的状态
+    ///
+    /// ClearFault只负责状态切换, 调用前应先确认ProtectionManager::clear成功。
+    pub fn request(&mut self, request: AxisRequest) -> Result<AxisState, AxisError> {
+        // NaN会使占空比变为NaN, 无穷大会使角度归一化无法结束
+        if let AxisRequest::Run { target, .. } = request {
+            if !target.is_finite() {
+                return Err(AxisError::InvalidTarget);
+            }
+        }
+        let invalid = AxisError::InvalidRequest {
+            state: self.state,
+            request,
+        };
+        self.state = match (self.state, request) {
+            (AxisState::Fault, AxisRequest::ClearFault) => {
+                self.fault = None;
+                AxisState::Idle
+            }
+            (AxisState::Idle, AxisRequest::Calibrate) => AxisState::Calibrating,
+            (AxisState::Idle, AxisRequest::Arm) if !self.calibrated => {
+                return Err(AxisError::NotCalibrated)
+            }
+            (AxisState::Idle, AxisRequest::Arm) => AxisState::Armed,
+            (
+                AxisState::Armed | AxisState::ClosedLoop,
+                AxisRequest::Run {
+                    control_type,
+                    target,
+                },
+            ) => {
+                self.control_type = control_type;
+                self.target = target;
+                AxisState::ClosedLoop
+            }
+            (AxisState::Idle | AxisState::Armed | AxisState::ClosedLoop, AxisRequest::Disarm) => {
+                AxisState::Idle
+            }
+            _ => return Err(invalid),
+        };
+        if self.state != AxisState::ClosedLoop {
+            self.control_type = ControlType::None;
+            self.target = 0.0;
+        }
+        debug!("axis: {:?} -> {:?}", request, self.state);
+        Ok(self.state)
+    }
+
+    /// 校准结束, 回到Idle
+    pub fn calibration_finished(&mut self, ok: bool) -> AxisState {
+        if self.state == AxisState::Calibrating {
+            self.calibrated = ok;
+            self.state = AxisState::Idle;
+        }
+        self.state
+    }
+
+    /// 保护跳闸, 任何状态都进入Fault
+    pub fn fault(&mut self, reason: TripReason) {
+        self.state = AxisState::Fault;
+        self.fault = Some(reason);
+        self.control_type = ControlType::None;
+        self.target = 0.0;
+    }
+
+    pub fn state(&self) -> AxisState {
+        self.state
+    }
+
+    pub fn calibrated(&self) -> bool {
+        self.calibrated
+    }
+
+    /// 是否需要使能栅极驱动和PWM
+    pub fn output_enabled(&self) -> bool {
+        self.state.output_enabled()
+    }
+
+    /// 当前生效的控制模式, ClosedLoop以外为ControlType::None
+    pub fn control_type(&self) -> ControlType {
+        self.control_type
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn status(&self) -> AxisStatus {
+        AxisStatus {
+            state: self.state,
+            control_type: self.control_type,
+            fault: self.fault,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUN: AxisRequest = AxisRequest::Run {
+        control_type: ControlType::Velocity,
+        target: 10.0,
+    };
+
+    #[test]
+    fn arm_run_disarm() {
+        let mut axis = Axis::new(true);
+        assert!(!axis.output_enabled());
+        assert_eq!(axis.request(AxisRequest::Arm), Ok(AxisState::Armed));
+        assert!(axis.output_enabled());
+        assert_eq!(axis.control_type(), ControlType::None);
+        assert_eq!(axis.request(RUN), Ok(AxisState::ClosedLoop));
+        assert_eq!(axis.control_type(), ControlType::Velocity);
+        assert_eq!(axis.target(), 10.0);
+        assert_eq!(axis.request(AxisRequest::Disarm), Ok(AxisState::Idle));
+        assert_eq!(axis.control_type(), ControlType::None);
+        assert!(!axis.output_enabled());
+    }
+
+    #[test]
+    fn non_finite_target_is_rejected() {
+        let mut axis = Axis::new(true);
+        axis.request(AxisRequest::Arm).unwrap();
+        axis.request(RUN).unwrap();
+        for target in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let run = AxisRequest::Run {
+                control_type: ControlType::Angle,
+                target,
+            };
+            assert_eq!(axis.request(run), Err(AxisError::InvalidTarget));
+        }
+        assert_eq!(axis.state(), AxisState::ClosedLoop);
+        assert_eq!(axis.control_type(), ControlType::Velocity);
+        assert_eq!(axis.target(), 10.0);
+    }
+
+    #[test]
+    fn arm_requires_calibration() {
+        let mut axis = Axis::new(false);
+        assert_eq!(
+            axis.request(AxisRequest::Arm),
+            Err(AxisError::NotCalibrated)
+        );
+        assert_eq!(
+            axis.request(RUN).unwrap_err(),
+            AxisError::InvalidRequest {
+                state: AxisState::Idle,
+                request: RUN,
+            }
+        );
+        assert_eq!(
+            axis.request(AxisRequest::Calibrate),
+            Ok(AxisState::Calibrating)
+        );
+        // 校准期间不接受其他请求
+        assert!(axis.request(AxisRequest::Disarm).is_err());
+        assert_eq!(axis.calibration_finished(true), AxisState::Idle);
+        assert!(axis.calibrated());
+        assert_eq!(axis.request(AxisRequest::Arm), Ok(AxisState::Armed));
+    }
+
+    #[test]
+    fn fault_blocks_until_cleared() {
+        let mut axis = Axis::new(true);
+        axis.request(AxisRequest::Arm).unwrap();
+        axis.request(RUN).unwrap();
+        axis.fault(TripReason::OverVoltage);
+        assert_eq!(axis.state(), AxisState::Fault);
+        assert!(!axis.output_enabled());
+        assert_eq!(axis.status().fault, Some(TripReason::OverVoltage));
+        assert!(axis.request(AxisRequest::Arm).is_err());
+        assert_eq!(axis.request(AxisRequest::ClearFault), Ok(AxisState::Idle));
+        assert_eq!(axis.status().fault, None);
+        assert_eq!(axis.request(AxisRequest::Arm), Ok(AxisState::Armed));
+    }
+
+    #[test]
+    fn failed_calibration_stays_uncalibrated() {
+        let mut axis = Axis::new(false);
+        axis.request(AxisRequest::Calibrate).unwrap();
+        assert_eq!(axis.calibration_finished(false), AxisState::Idle);
+        assert_eq!(
+            axis.request(AxisRequest::Arm),
+            Err(AxisError::NotCalibrated)
+        );
+    }
+}
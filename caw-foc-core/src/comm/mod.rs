@@ -0,0 +1,404 @@
+//! CAN和USART上报数据的编码
+
+use core::fmt::{self, Write};
+
+use crate::{
+    axis::{AxisRequest, AxisState, AxisStatus},
+    config::{ConfigParam, ConfigRequest},
+    monitor::Readings,
+    motor::ControlType,
+    protection::TripReason,
+    timing::{ProfileReport, TimingStats},
+};
+
+/// DRV8323故障位域上报帧ID
+pub const CAN_ID_FAULTS: u16 = 0x080;
+/// 母线电压和温度上报帧ID
+pub const CAN_ID_POWER: u16 = 0x081;
+/// 状态机状态上报帧ID
+pub const CAN_ID_AXIS_STATUS: u16 = 0x082;
+/// CPU负载和控制循环执行时间上报帧ID
+pub const CAN_ID_PROFILE_LOOP: u16 = 0x083;
+/// Motor各段代码执行时间上报帧ID
+pub const CAN_ID_PROFILE_SECTIONS: u16 = 0x084;
+/// 状态切换请求帧ID
+pub const CAN_ID_AXIS_REQUEST: u16 = 0x100;
+/// 配置修改请求帧ID
+pub const CAN_ID_CONFIG_REQUEST: u16 = 0x101;
+
+/// USART命令中控制模式的名称, 下标为CAN帧中的编码
+const CONTROL_TYPES: [(ControlType, &str); 7] = [
+    (ControlType::None, "none"),
+    (ControlType::VelocityOpenLoop, "vel_ol"),
+    (ControlType::Velocity, "vel"),
+    (ControlType::Angle, "angle"),
+    (ControlType::Torque, "torque"),
+    (ControlType::AngleOpenLoop, "angle_ol"),
+    (ControlType::FocCurrent, "current"),
+];
+
+fn control_type_code(control_type: ControlType) -> u8 {
+    CONTROL_TYPES
+        .iter()
+        .position(|(c, _)| *c == control_type)
+        .unwrap_or(0) as u8
+}
+
+/// 故障位域的CAN帧数据, 4字节小端
+pub fn encode_faults(bits: u32) -> [u8; 4] {
+    bits.to_le_bytes()
+}
+
+pub fn decode_faults(data: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// 故障位域的USART文本行, 格式为`FAULT:XXXXXX\r\n`
+pub fn write_fault_line<W: Write>(w: &mut W, bits: u32) -> fmt::Result {
+    write!(w, "FAULT:{:06X}\r\n", bits)
+}
+
+/// 母线电压和温度的CAN帧数据, 8字节小端
+///
+/// vbus(u16, mV) fet_temperature(i16, 0.1°C) mcu_temperature(i16, 0.1°C) flags(u8) 保留(u8),
+/// flags的bit0为NTC故障。
+pub fn encode_power(r: &Readings) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    buf[0..2].copy_from_slice(&((r.vbus * 1000.0) as u16).to_le_bytes());
+    buf[2..4].copy_from_slice(&((r.fet_temperature * 10.0) as i16).to_le_bytes());
+    buf[4..6].copy_from_slice(&((r.mcu_temperature * 10.0) as i16).to_le_bytes());
+    buf[6] = r.ntc_fault as u8;
+    buf
+}
+
+pub fn decode_power(data: &[u8]) -> Option<Readings> {
+    let data: &[u8; 8] = data.get(..8)?.try_into().ok()?;
+    Some(Readings {
+        vbus: u16::from_le_bytes([data[0], data[1]]) as f32 / 1000.0,
+        fet_temperature: i16::from_le_bytes([data[2], data[3]]) as f32 / 10.0,
+        mcu_temperature: i16::from_le_bytes([data[4], data[5]]) as f32 / 10.0,
+        ntc_fault: data[6] & 0x1 != 0,
+    })
+}
+
+/// 解码状态切换请求
+///
+/// data[0]: 0 Disarm, 1 Calibrate, 2 Arm, 3 Run, 4 ClearFault;
+/// Run时data[1]为控制模式编码, data[2..6]为f32小端的目标值, NaN和无穷大视为无效帧。
+pub fn decode_axis_request(data: &[u8]) -> Option<AxisRequest> {
+    match *data.first()? {
+        0 => Some(AxisRequest::Disarm),
+        1 => Some(AxisRequest::Calibrate),
+        2 => Some(AxisRequest::Arm),
+        3 => Some(AxisRequest::Run {
+            control_type: CONTROL_TYPES.get(*data.get(1)? as usize)?.0,
+            target: Some(f32::from_le_bytes(data.get(2..6)?.try_into().ok()?))
+                .filter(|t| t.is_finite())?,
+        }),
+        4 => Some(AxisRequest::ClearFault),
+        _ => None,
+    }
+}
+
+/// 解析USART命令行: `CAL` `ARM` `DISARM` `CLEAR` `RUN <模式> <目标值>`, 目标值不接受`NaN`和`inf`
+pub fn parse_axis_command(line: &str) -> Option<AxisRequest> {
+    let mut words = line.split_whitespace();
+    let request = match words.next()? {
+        "CAL" => AxisRequest::Calibrate,
+        "ARM" => AxisRequest::Arm,
+        "DISARM" => AxisRequest::Disarm,
+        "CLEAR" => AxisRequest::ClearFault,
+        "RUN" => {
+            let name = words.next()?;
+            let control_type = CONTROL_TYPES.iter().find(|(_, n)| *n == name)?.0;
+            AxisRequest::Run {
+                control_type,
+                target: words.next()?.parse().ok().filter(|t: &f32| t.is_finite())?,
+            }
+        }
+        _ => return None,
+    };
+    words.next().is_none().then_some(request)
+}
+
+/// 解码配置修改请求
+///
+/// data[0]: 0 Save, 1 Set; Set时data[1]为参数编码, data[2..6]为f32小端的值。
+pub fn decode_config_request(data: &[u8]) -> Option<ConfigRequest> {
+    match *data.first()? {
+        0 => Some(ConfigRequest::Save),
+        1 => Some(ConfigRequest::Set {
+            param: ConfigParam::from_code(*data.get(1)?)?,
+            value: f32::from_le_bytes(data.get(2..6)?.try_into().ok()?),
+        }),
+        _ => None,
+    }
+}
+
+/// 解析USART配置命令: `SET <参数> <值>` `SAVE`
+pub fn parse_config_command(line: &str) -> Option<ConfigRequest> {
+    let mut words = line.split_whitespace();
+    let request = match words.next()? {
+        "SAVE" => ConfigRequest::Save,
+        "SET" => ConfigRequest::Set {
+            param: ConfigParam::from_name(words.next()?)?,
+            value: words.next()?.parse().ok()?,
+        },
+        _ => return None,
+    };
+    words.next().is_none().then_some(request)
+}
+
+fn trip_reason_code(reason: Option<TripReason>) -> (u8, u32) {
+    match reason {
+        None => (0, 0),
+        Some(TripReason::OverVoltage) => (1, 0),
+        Some(TripReason::UnderVoltage) => (2, 0),
+        Some(TripReason::FetOverTemperature) => (3, 0),
+        Some(TripReason::McuOverTemperature) => (4, 0),
+        Some(TripReason::NtcFault) => (5, 0),
+        Some(TripReason::OverCurrent) => (6, 0),
+        Some(TripReason::DrvFault(bits)) => (7, bits),
+    }
+}
+
+/// 状态机状态的CAN帧数据, 8字节
+///
+/// state(u8) control_type(u8) 跳闸原因(u8) 保留(u8) DRV8323故障位域(u32小端, 仅原因为7时有效)。
+/// state: 0 Idle, 1 Calibrating, 2 Armed, 3 ClosedLoop, 4 Fault;
+/// 跳闸原因: 0无, 1过压, 2欠压, 3功率管过温, 4 MCU过温, 5 NTC故障, 6过流, 7 DRV8323故障。
+pub fn encode_axis_status(status: &AxisStatus) -> [u8; 8] {
+    let (reason, bits) = trip_reason_code(status.fault);
+    let mut buf = [0u8; 8];
+    buf[0] = match status.state {
+        AxisState::Idle => 0,
+        AxisState::Calibrating => 1,
+        AxisState::Armed => 2,
+        AxisState::ClosedLoop => 3,
+        AxisState::Fault => 4,
+    };
+    buf[1] = control_type_code(status.control_type);
+    buf[2] = reason;
+    buf[4..8].copy_from_slice(&bits.to_le_bytes());
+    buf
+}
+
+/// 状态机状态的USART文本行, 例如`STATE:Fault OverVoltage\r\n`
+pub fn write_status_line<W: Write>(w: &mut W, status: &AxisStatus) -> fmt::Result {
+    write!(w, "STATE:{:?}", status.state)?;
+    if status.state == AxisState::ClosedLoop {
+        write!(
+            w,
+            " {}",
+            CONTROL_TYPES[control_type_code(status.control_type) as usize].1
+        )?;
+    }
+    if let Some(reason) = status.fault {
+        write!(w, " {:?}", reason)?;
+    }
+    write!(w, "\r\n")
+}
+
+fn saturate_u16(v: u32) -> [u8; 2] {
+    (v.min(u16::MAX as u32) as u16).to_le_bytes()
+}
+
+/// CPU负载和控制循环的CAN帧数据, 8字节小端
+///
+/// cpu_load(u16, 0.01%) 执行时间平均值(u16) 执行时间最大值(u16) 丢失触发次数(u16),
+/// 时间单位为CPU周期, 超出u16范围时取最大值。
+pub fn encode_profile_loop(report: &ProfileReport) -> [u8; 8] {
+    let t = &report.loop_timing;
+    let mut buf = [0u8; 8];
+    buf[0..2].copy_from_slice(&((report.cpu_load * 10000.0) as u16).to_le_bytes());
+    buf[2..4].copy_from_slice(&saturate_u16(t.execution.average()));
+    buf[4..6].copy_from_slice(&saturate_u16(t.execution.max));
+    buf[6..8].copy_from_slice(&saturate_u16(t.overruns));
+    buf
+}
+
+/// Motor各段代码执行时间的CAN帧数据, 8字节小端, 单位为CPU周期
+///
+/// step平均值(u16) step最大值(u16) set_phase_voltage平均值(u16) fast_sincos平均值(u16)。
+pub fn encode_profile_sections(report: &ProfileReport) -> [u8; 8] {
+    let m = &report.motor;
+    let mut buf = [0u8; 8];
+    buf[0..2].copy_from_slice(&saturate_u16(m.step.average()));
+    buf[2..4].copy_from_slice(&saturate_u16(m.step.max));
+    buf[4..6].copy_from_slice(&saturate_u16(m.set_phase_voltage.average()));
+    buf[6..8].copy_from_slice(&saturate_u16(report.sincos.average()));
+    buf
+}
+
+fn write_stats<W: Write>(w: &mut W, name: &str, s: &TimingStats) -> fmt::Result {
+    write!(w, " {}={}/{}", name, s.average(), s.max)
+}
+
+/// 性能统计的USART文本行, 各段为`平均值/最大值`, 单位为CPU周期, 例如
+/// `PROF:load=12.50% loop=2400/3100 step=2100/2800 spv=600/650 sincos=40/44 overruns=0\r\n`
+pub fn write_profile_line<W: Write>(w: &mut W, report: &ProfileReport) -> fmt::Result {
+    write!(w, "PROF:load={:.2}%", report.cpu_load * 100.0)?;
+    write_stats(w, "loop", &report.loop_timing.execution)?;
+    write_stats(w, "step", &report.motor.step)?;
+    write_stats(w, "spv", &report.motor.set_phase_voltage)?;
+    write_stats(w, "sincos", &report.sincos)?;
+    write!(w, " overruns={}\r\n", report.loop_timing.overruns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_frame_round_trip() {
+        let bits = 0x2A_5A5A;
+        assert_eq!(decode_faults(&encode_faults(bits)), Some(bits));
+        assert_eq!(decode_faults(&[0; 3]), None);
+    }
+
+    #[test]
+    fn power_frame_round_trip() {
+        let r = Readings {
+            vbus: 24.5,
+            fet_temperature: -12.5,
+            mcu_temperature: 41.0,
+            ntc_fault: true,
+        };
+        assert_eq!(decode_power(&encode_power(&r)), Some(r));
+        assert_eq!(decode_power(&[0; 7]), None);
+    }
+
+    #[test]
+    fn axis_request_frames() {
+        assert_eq!(decode_axis_request(&[2]), Some(AxisRequest::Arm));
+        let mut data = [3, 2, 0, 0, 0, 0];
+        data[2..6].copy_from_slice(&(-20.0f32).to_le_bytes());
+        assert_eq!(
+            decode_axis_request(&data),
+            Some(AxisRequest::Run {
+                control_type: ControlType::Velocity,
+                target: -20.0
+            })
+        );
+        // 缺少目标值或未知的控制模式
+        assert_eq!(decode_axis_request(&data[..5]), None);
+        assert_eq!(decode_axis_request(&[3, 9, 0, 0, 0, 0]), None);
+        assert_eq!(decode_axis_request(&[]), None);
+        for target in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            data[2..6].copy_from_slice(&target.to_le_bytes());
+            assert_eq!(decode_axis_request(&data), None);
+        }
+    }
+
+    #[test]
+    fn axis_commands() {
+        assert_eq!(parse_axis_command("ARM\r\n"), Some(AxisRequest::Arm));
+        assert_eq!(
+            parse_axis_command("RUN torque 1.5"),
+            Some(AxisRequest::Run {
+                control_type: ControlType::Torque,
+                target: 1.5
+            })
+        );
+        assert_eq!(parse_axis_command("RUN torque"), None);
+        for target in ["NaN", "inf", "-infinity"] {
+            assert_eq!(parse_axis_command(&format!("RUN vel {target}")), None);
+        }
+        assert_eq!(parse_axis_command("ARM now"), None);
+        assert_eq!(parse_axis_command(""), None);
+    }
+
+    #[test]
+    fn axis_status_encoding() {
+        let status = AxisStatus {
+            state: AxisState::Fault,
+            control_type: ControlType::None,
+            fault: Some(TripReason::DrvFault(0x400)),
+        };
+        assert_eq!(encode_axis_status(&status), [4, 0, 7, 0, 0, 4, 0, 0]);
+        let mut line = String::new();
+        write_status_line(&mut line, &status).unwrap();
+        assert_eq!(line, "STATE:Fault DrvFault(1024)\r\n");
+
+        let status = AxisStatus {
+            state: AxisState::ClosedLoop,
+            control_type: ControlType::Velocity,
+            fault: None,
+        };
+        let mut line = String::new();
+        write_status_line(&mut line, &status).unwrap();
+        assert_eq!(line, "STATE:ClosedLoop vel\r\n");
+    }
+
+    #[test]
+    fn fault_line_format() {
+        let mut line = String::new();
+        write_fault_line(&mut line, 0x400).unwrap();
+        assert_eq!(line, "FAULT:000400\r\n");
+    }
+
+    #[test]
+    fn config_requests() {
+        assert_eq!(decode_config_request(&[0]), Some(ConfigRequest::Save));
+        let mut data = [1, 0, 0, 0, 0, 0];
+        data[2..6].copy_from_slice(&14.0f32.to_le_bytes());
+        assert_eq!(
+            decode_config_request(&data),
+            Some(ConfigRequest::Set {
+                param: ConfigParam::PolePairs,
+                value: 14.0
+            })
+        );
+        assert_eq!(decode_config_request(&[1, 99, 0, 0, 0, 0]), None);
+        assert_eq!(decode_config_request(&data[..5]), None);
+
+        assert_eq!(parse_config_command("SAVE\r\n"), Some(ConfigRequest::Save));
+        assert_eq!(
+            parse_config_command("SET pid_current_p 2.5"),
+            Some(ConfigRequest::Set {
+                param: ConfigParam::PidCurrentP,
+                value: 2.5
+            })
+        );
+        assert_eq!(parse_config_command("SET unknown 1"), None);
+        assert_eq!(parse_config_command("SET pole_pairs"), None);
+        assert_eq!(parse_config_command("ARM"), None);
+    }
+
+    fn report() -> ProfileReport {
+        let mut r = ProfileReport::new();
+        r.cpu_load = 0.125;
+        for v in [2000, 2800] {
+            r.loop_timing.execution.record(v);
+        }
+        r.loop_timing.overruns = 3;
+        r.motor.step.record(100_000);
+        r.motor.set_phase_voltage.record(600);
+        r.sincos.record(40);
+        r
+    }
+
+    #[test]
+    fn profile_frames() {
+        let r = report();
+        assert_eq!(
+            encode_profile_loop(&r),
+            [0xE2, 0x04, 0x60, 0x09, 0xF0, 0x0A, 3, 0]
+        );
+        // 超出u16范围的step取最大值
+        assert_eq!(
+            encode_profile_sections(&r),
+            [0xFF, 0xFF, 0xFF, 0xFF, 0x58, 0x02, 40, 0]
+        );
+    }
+
+    #[test]
+    fn profile_line_format() {
+        let mut line = String::new();
+        write_profile_line(&mut line, &report()).unwrap();
+        assert_eq!(
+            line,
+            "PROF:load=12.50% loop=2400/2800 step=100000/100000 spv=600/600 sincos=40/40 overruns=3\r\n"
+        );
+    }
+}
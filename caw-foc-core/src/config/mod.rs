@@ -0,0 +1,551 @@
+use crate::{
+    drv8323::registers::{Drv8323Config, Register},
+    protection::ProtectionConfig,
+};
+
+pub const CONFIG_MAGIC: u32 = 0x4357_4346;
+pub const CONFIG_VERSION: u16 = 2;
+
+// 记录格式: magic(4) version(2) len(2) seq(4) payload crc32(4)
+const HEADER_LEN: usize = 12;
+const PAYLOAD_LEN: usize = 114;
+pub const RECORD_LEN: usize = HEADER_LEN + PAYLOAD_LEN + 4;
+
+// 极对数的合理上限
+const MAX_POLE_PAIRS: u32 = 64;
+
+/// 可以通过CAN/USART设置的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigParam {
+    PolePairs,
+    SensorDirection,
+    ZeroElectricAngle,
+    VoltagePowerSupply,
+    VoltageLimit,
+    VelocityLimit,
+    ShuntResistor,
+    PidVelocityP,
+    PidVelocityI,
+    PidVelocityD,
+    PidAngleP,
+    PidCurrentP,
+    PidCurrentI,
+    CanBitrate,
+    CanDataBitrate,
+    VbusMax,
+    VbusMin,
+    VbusHysteresis,
+    FetTempDerate,
+    FetTempMax,
+    McuTempMax,
+    TempHysteresis,
+    CurrentContinuous,
+    CurrentPeak,
+    PeakTime,
+}
+
+/// USART命令中参数的名称, 下标为CAN帧中的编码
+const CONFIG_PARAMS: [(ConfigParam, &str); 25] = [
+    (ConfigParam::PolePairs, "pole_pairs"),
+    (ConfigParam::SensorDirection, "sensor_direction"),
+    (ConfigParam::ZeroElectricAngle, "zero_electric_angle"),
+    (ConfigParam::VoltagePowerSupply, "voltage_power_supply"),
+    (ConfigParam::VoltageLimit, "voltage_limit"),
+    (ConfigParam::VelocityLimit, "velocity_limit"),
+    (ConfigParam::ShuntResistor, "shunt_resistor"),
+    (ConfigParam::PidVelocityP, "pid_velocity_p"),
+    (ConfigParam::PidVelocityI, "pid_velocity_i"),
+    (ConfigParam::PidVelocityD, "pid_velocity_d"),
+    (ConfigParam::PidAngleP, "pid_angle_p"),
+    (ConfigParam::PidCurrentP, "pid_current_p"),
+    (ConfigParam::PidCurrentI, "pid_current_i"),
+    (ConfigParam::CanBitrate, "can_bitrate"),
+    (ConfigParam::CanDataBitrate, "can_data_bitrate"),
+    (ConfigParam::VbusMax, "vbus_max"),
+    (ConfigParam::VbusMin, "vbus_min"),
+    (ConfigParam::VbusHysteresis, "vbus_hysteresis"),
+    (ConfigParam::FetTempDerate, "fet_temp_derate"),
+    (ConfigParam::FetTempMax, "fet_temp_max"),
+    (ConfigParam::McuTempMax, "mcu_temp_max"),
+    (ConfigParam::TempHysteresis, "temp_hysteresis"),
+    (ConfigParam::CurrentContinuous, "current_continuous"),
+    (ConfigParam::CurrentPeak, "current_peak"),
+    (ConfigParam::PeakTime, "peak_time"),
+];
+
+impl ConfigParam {
+    pub fn from_name(name: &str) -> Option<Self> {
+        CONFIG_PARAMS
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(p, _)| *p)
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        CONFIG_PARAMS.get(code as usize).map(|(p, _)| *p)
+    }
+}
+
+/// 配置修改请求, 修改保存后重启生效
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigRequest {
+    /// 修改参数, 不合法时保持原值
+    Set { param: ConfigParam, value: f32 },
+    /// 将当前配置写入Flash
+    Save,
+    /// 传感器校准完成, 更新对齐结果并保存
+    SensorAlignment {
+        sensor_direction: i32,
+        zero_electric_angle: f32,
+    },
+}
+
+/// 需要掉电保存的驱动器配置
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriveConfig {
+    pub pole_pairs: u32,
+    pub sensor_aligned: bool, // sensor_direction和zero_electric_angle是否已校准
+    pub sensor_direction: i32,
+    pub zero_electric_angle: f32,
+    pub voltage_power_supply: f32,
+    pub voltage_limit: f32,
+    pub velocity_limit: f32,
+    pub shunt_resistor: f32,
+    pub pid_velocity_p: f32,
+    pub pid_velocity_i: f32,
+    pub pid_velocity_d: f32,
+    pub pid_angle_p: f32,
+    pub pid_current_p: f32,
+    pub pid_current_i: f32,
+    pub drv: Drv8323Config,
+    pub can_bitrate: u32,
+    pub can_data_bitrate: u32,
+    pub protection: ProtectionConfig,
+}
+
+impl Default for DriveConfig {
+    fn default() -> Self {
+        Self {
+            pole_pairs: 7,
+            sensor_aligned: false,
+            sensor_direction: 1,
+            zero_electric_angle: 0.0,
+            voltage_power_supply: 12.0,
+            voltage_limit: 6.0,
+            velocity_limit: 20.0,
+            shunt_resistor: 0.01,
+            pid_velocity_p: 0.5,
+            pid_velocity_i: 10.0,
+            pid_velocity_d: 0.0,
+            pid_angle_p: 20.0,
+            pid_current_p: 3.0,
+            pid_current_i: 300.0,
+            drv: Drv8323Config::default(),
+            can_bitrate: 500_000,
+            can_data_bitrate: 5_000_000,
+            protection: ProtectionConfig::default(),
+        }
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.put(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.put(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.put(&v.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0u8; N];
+        out.copy_from_slice(&self.buf[self.pos..self.pos + N]);
+        self.pos += N;
+        out
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.take())
+    }
+}
+
+/// 整数参数只接受能精确表示的值, 不截断小数, 也不把负数饱和为0
+fn exact_u32(value: f32) -> Option<u32> {
+    let v = value as u32;
+    (v as f32 == value && value < u32::MAX as f32).then_some(v)
+}
+
+fn exact_i32(value: f32) -> Option<i32> {
+    let v = value as i32;
+    (v as f32 == value && value < i32::MAX as f32).then_some(v)
+}
+
+impl DriveConfig {
+    /// 设置参数, 整数参数的值不是整数或设置后的配置不合法时保持原值并返回该参数
+    pub fn set(&mut self, param: ConfigParam, value: f32) -> Result<(), ConfigParam> {
+        let mut cfg = *self;
+        let p = &mut cfg.protection;
+        match param {
+            ConfigParam::PolePairs => cfg.pole_pairs = exact_u32(value).ok_or(param)?,
+            ConfigParam::SensorDirection => cfg.sensor_direction = exact_i32(value).ok_or(param)?,
+            ConfigParam::ZeroElectricAngle => cfg.zero_electric_angle = value,
+            ConfigParam::VoltagePowerSupply => cfg.voltage_power_supply = value,
+            ConfigParam::VoltageLimit => cfg.voltage_limit = value,
+            ConfigParam::VelocityLimit => cfg.velocity_limit = value,
+            ConfigParam::ShuntResistor => cfg.shunt_resistor = value,
+            ConfigParam::PidVelocityP => cfg.pid_velocity_p = value,
+            ConfigParam::PidVelocityI => cfg.pid_velocity_i = value,
+            ConfigParam::PidVelocityD => cfg.pid_velocity_d = value,
+            ConfigParam::PidAngleP => cfg.pid_angle_p = value,
+            ConfigParam::PidCurrentP => cfg.pid_current_p = value,
+            ConfigParam::PidCurrentI => cfg.pid_current_i = value,
+            ConfigParam::CanBitrate => cfg.can_bitrate = exact_u32(value).ok_or(param)?,
+            ConfigParam::CanDataBitrate => cfg.can_data_bitrate = exact_u32(value).ok_or(param)?,
+            ConfigParam::VbusMax => p.vbus_max = value,
+            ConfigParam::VbusMin => p.vbus_min = value,
+            ConfigParam::VbusHysteresis => p.vbus_hysteresis = value,
+            ConfigParam::FetTempDerate => p.fet_temp_derate = value,
+            ConfigParam::FetTempMax => p.fet_temp_max = value,
+            ConfigParam::McuTempMax => p.mcu_temp_max = value,
+            ConfigParam::TempHysteresis => p.temp_hysteresis = value,
+            ConfigParam::CurrentContinuous => p.current_continuous = value,
+            ConfigParam::CurrentPeak => p.current_peak = value,
+            ConfigParam::PeakTime => p.peak_time = value,
+        }
+        cfg.validate()?;
+        *self = cfg;
+        Ok(())
+    }
+
+    /// 检查参数范围, 返回第一个不合法的参数
+    pub fn validate(&self) -> Result<(), ConfigParam> {
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        let non_negative = |v: f32| v.is_finite() && v >= 0.0;
+        let p = &self.protection;
+        let checks = [
+            (
+                ConfigParam::PolePairs,
+                self.pole_pairs > 0 && self.pole_pairs <= MAX_POLE_PAIRS,
+            ),
+            (
+                ConfigParam::SensorDirection,
+                self.sensor_direction == 1 || self.sensor_direction == -1,
+            ),
+            (
+                ConfigParam::ZeroElectricAngle,
+                self.zero_electric_angle.is_finite(),
+            ),
+            (
+                ConfigParam::VoltagePowerSupply,
+                positive(self.voltage_power_supply),
+            ),
+            (
+                ConfigParam::VoltageLimit,
+                positive(self.voltage_limit) && self.voltage_limit <= self.voltage_power_supply,
+            ),
+            (ConfigParam::VelocityLimit, positive(self.velocity_limit)),
+            (ConfigParam::ShuntResistor, positive(self.shunt_resistor)),
+            (ConfigParam::PidVelocityP, non_negative(self.pid_velocity_p)),
+            (ConfigParam::PidVelocityI, non_negative(self.pid_velocity_i)),
+            (ConfigParam::PidVelocityD, non_negative(self.pid_velocity_d)),
+            (ConfigParam::PidAngleP, non_negative(self.pid_angle_p)),
+            (ConfigParam::PidCurrentP, non_negative(self.pid_current_p)),
+            (ConfigParam::PidCurrentI, non_negative(self.pid_current_i)),
+            (ConfigParam::CanBitrate, self.can_bitrate > 0),
+            (ConfigParam::CanDataBitrate, self.can_data_bitrate > 0),
+            (ConfigParam::VbusMax, positive(p.vbus_max)),
+            (
+                ConfigParam::VbusMin,
+                positive(p.vbus_min) && p.vbus_min < p.vbus_max,
+            ),
+            (ConfigParam::VbusHysteresis, non_negative(p.vbus_hysteresis)),
+            (ConfigParam::FetTempDerate, p.fet_temp_derate.is_finite()),
+            (
+                ConfigParam::FetTempMax,
+                p.fet_temp_max.is_finite() && p.fet_temp_max > p.fet_temp_derate,
+            ),
+            (ConfigParam::McuTempMax, p.mcu_temp_max.is_finite()),
+            (ConfigParam::TempHysteresis, non_negative(p.temp_hysteresis)),
+            (
+                ConfigParam::CurrentContinuous,
+                positive(p.current_continuous),
+            ),
+            (
+                ConfigParam::CurrentPeak,
+                positive(p.current_peak) && p.current_peak > p.current_continuous,
+            ),
+            (ConfigParam::PeakTime, positive(p.peak_time)),
+        ];
+        match checks.iter().find(|(_, ok)| !ok) {
+            Some((param, _)) => Err(*param),
+            None => Ok(()),
+        }
+    }
+
+    /// 编码为带序号和CRC的记录
+    pub fn encode(&self, seq: u32) -> [u8; RECORD_LEN] {
+        let mut buf = [0u8; RECORD_LEN];
+        let mut w = Writer {
+            buf: &mut buf,
+            pos: 0,
+        };
+        w.u32(CONFIG_MAGIC);
+        w.u16(CONFIG_VERSION);
+        w.u16(PAYLOAD_LEN as u16);
+        w.u32(seq);
+
+        w.u32(self.pole_pairs);
+        w.u32(self.sensor_aligned as u32);
+        w.u32(self.sensor_direction as u32);
+        w.f32(self.zero_electric_angle);
+        w.f32(self.voltage_power_supply);
+        w.f32(self.voltage_limit);
+        w.f32(self.velocity_limit);
+        w.f32(self.shunt_resistor);
+        w.f32(self.pid_velocity_p);
+        w.f32(self.pid_velocity_i);
+        w.f32(self.pid_velocity_d);
+        w.f32(self.pid_angle_p);
+        w.f32(self.pid_current_p);
+        w.f32(self.pid_current_i);
+        // DRV8323寄存器保存为11位寄存器数据
+        w.u16(self.drv.driver_control.encode());
+        w.u16(self.drv.gate_drive_hs.encode());
+        w.u16(self.drv.gate_drive_ls.encode());
+        w.u16(self.drv.ocp_control.encode());
+        w.u16(self.drv.csa_control.encode());
+        w.u32(self.can_bitrate);
+        w.u32(self.can_data_bitrate);
+        let p = &self.protection;
+        for v in [
+            p.vbus_max,
+            p.vbus_min,
+            p.vbus_hysteresis,
+            p.fet_temp_derate,
+            p.fet_temp_max,
+            p.mcu_temp_max,
+            p.temp_hysteresis,
+            p.current_continuous,
+            p.current_peak,
+            p.peak_time,
+        ] {
+            w.f32(v);
+        }
+
+        let crc = crc32(&buf[..RECORD_LEN - 4]);
+        buf[RECORD_LEN - 4..].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// 解码记录, 返回(配置, 序号); magic、版本、长度或CRC不符, 或参数不合法时返回None
+    pub fn decode(buf: &[u8]) -> Option<(Self, u32)> {
+        if buf.len() < RECORD_LEN {
+            return None;
+        }
+        let crc = u32::from_le_bytes(buf[RECORD_LEN - 4..RECORD_LEN].try_into().ok()?);
+        if crc != crc32(&buf[..RECORD_LEN - 4]) {
+            return None;
+        }
+
+        let mut r = Reader { buf, pos: 0 };
+        if r.u32() != CONFIG_MAGIC || r.u16() != CONFIG_VERSION || r.u16() as usize != PAYLOAD_LEN {
+            return None;
+        }
+        let seq = r.u32();
+
+        let cfg = Self {
+            pole_pairs: r.u32(),
+            sensor_aligned: r.u32() != 0,
+            sensor_direction: r.u32() as i32,
+            zero_electric_angle: r.f32(),
+            voltage_power_supply: r.f32(),
+            voltage_limit: r.f32(),
+            velocity_limit: r.f32(),
+            shunt_resistor: r.f32(),
+            pid_velocity_p: r.f32(),
+            pid_velocity_i: r.f32(),
+            pid_velocity_d: r.f32(),
+            pid_angle_p: r.f32(),
+            pid_current_p: r.f32(),
+            pid_current_i: r.f32(),
+            drv: Drv8323Config {
+                driver_control: Register::decode(r.u16()),
+                gate_drive_hs: Register::decode(r.u16()),
+                gate_drive_ls: Register::decode(r.u16()),
+                ocp_control: Register::decode(r.u16()),
+                csa_control: Register::decode(r.u16()),
+            },
+            can_bitrate: r.u32(),
+            can_data_bitrate: r.u32(),
+            protection: ProtectionConfig {
+                vbus_max: r.f32(),
+                vbus_min: r.f32(),
+                vbus_hysteresis: r.f32(),
+                fet_temp_derate: r.f32(),
+                fet_temp_max: r.f32(),
+                mcu_temp_max: r.f32(),
+                temp_hysteresis: r.f32(),
+                current_continuous: r.f32(),
+                current_peak: r.f32(),
+                peak_time: r.f32(),
+            },
+        };
+        if let Err(param) = cfg.validate() {
+            warn!("config record rejected: invalid {:?}", param);
+            return None;
+        }
+        Some((cfg, seq))
+    }
+}
+
+/// CRC-32 (IEEE 802.3)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let cfg = DriveConfig {
+            sensor_aligned: true,
+            sensor_direction: -1,
+            zero_electric_angle: 1.25,
+            protection: ProtectionConfig {
+                vbus_max: 50.0,
+                peak_time: 0.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let buf = cfg.encode(42);
+        assert_eq!(DriveConfig::decode(&buf), Some((cfg, 42)));
+    }
+
+    #[test]
+    fn corrupted_record_is_rejected() {
+        let mut buf = DriveConfig::default().encode(1);
+        buf[20] ^= 0x01;
+        assert_eq!(DriveConfig::decode(&buf), None);
+        assert_eq!(DriveConfig::decode(&buf[..RECORD_LEN - 1]), None);
+        // 擦除后的Flash
+        assert_eq!(DriveConfig::decode(&[0xFF; RECORD_LEN]), None);
+    }
+
+    #[test]
+    fn invalid_record_is_rejected() {
+        let cfg = DriveConfig {
+            pole_pairs: 0,
+            ..Default::default()
+        };
+        assert_eq!(cfg.validate(), Err(ConfigParam::PolePairs));
+        assert_eq!(DriveConfig::decode(&cfg.encode(1)), None);
+        let cfg = DriveConfig {
+            pid_current_p: f32::NAN,
+            ..Default::default()
+        };
+        assert_eq!(DriveConfig::decode(&cfg.encode(1)), None);
+    }
+
+    #[test]
+    fn set_parameter() {
+        let mut cfg = DriveConfig::default();
+        assert_eq!(cfg.set(ConfigParam::PolePairs, 14.0), Ok(()));
+        assert_eq!(cfg.pole_pairs, 14);
+        assert_eq!(cfg.set(ConfigParam::CurrentPeak, 30.0), Ok(()));
+        assert_eq!(cfg.protection.current_peak, 30.0);
+        // 不合法的值不修改配置
+        assert_eq!(
+            cfg.set(ConfigParam::VoltageLimit, 100.0),
+            Err(ConfigParam::VoltageLimit)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::ShuntResistor, f32::INFINITY),
+            Err(ConfigParam::ShuntResistor)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::PolePairs, -1.0),
+            Err(ConfigParam::PolePairs)
+        );
+        assert_eq!(cfg.voltage_limit, DriveConfig::default().voltage_limit);
+        assert_eq!(cfg.pole_pairs, 14);
+    }
+
+    #[test]
+    fn integer_parameters_are_not_truncated() {
+        let mut cfg = DriveConfig::default();
+        assert_eq!(
+            cfg.set(ConfigParam::PolePairs, 7.9),
+            Err(ConfigParam::PolePairs)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::SensorDirection, 0.5),
+            Err(ConfigParam::SensorDirection)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::CanBitrate, -500_000.0),
+            Err(ConfigParam::CanBitrate)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::CanDataBitrate, f32::NAN),
+            Err(ConfigParam::CanDataBitrate)
+        );
+        assert_eq!(
+            cfg.set(ConfigParam::CanBitrate, 1e10),
+            Err(ConfigParam::CanBitrate)
+        );
+        assert_eq!(cfg, DriveConfig::default());
+        assert_eq!(cfg.set(ConfigParam::SensorDirection, -1.0), Ok(()));
+        assert_eq!(cfg.sensor_direction, -1);
+    }
+}
@@ -0,0 +1,50 @@
+use embassy_time::Instant;
+
+/// 按时间差计算时的最小周期(秒), 时间戳相同时限幅
+const MIN_SAMPLE_TIME: f32 = 1e-6;
+
+pub struct LowPassFilter {
+    pub tf: f32, // 时间常数(秒)
+    y_prev: f32,
+    timestamp_prev: u64,
+    sample_time: Option<f32>, // 固定的调用周期(秒), None时按两次调用的时间差计算
+}
+
+impl LowPassFilter {
+    pub fn new(tf: f32) -> Self {
+        Self {
+            tf,
+            y_prev: 0.0,
+            timestamp_prev: Instant::now().as_micros(),
+            sample_time: None,
+        }
+    }
+
+    /// 按固定周期调用时设置, 不再读取Instant计算时间差
+    pub fn set_sample_time(&mut self, ts: f32) {
+        self.sample_time = Some(ts);
+    }
+
+    pub fn update(&mut self, x: f32) -> f32 {
+        if let Some(ts) = self.sample_time {
+            return self.filter(x, ts);
+        }
+        let now_us = Instant::now().as_micros();
+        let ts = now_us.saturating_sub(self.timestamp_prev) as f32 * 1e-6;
+        self.timestamp_prev = now_us;
+        if ts > 0.3 {
+            // 长时间未调用, 直接采用当前值
+            self.y_prev = x;
+            return x;
+        }
+
+        self.filter(x, ts.max(MIN_SAMPLE_TIME))
+    }
+
+    fn filter(&mut self, x: f32, ts: f32) -> f32 {
+        let alpha = self.tf / (self.tf + ts);
+        let y = alpha * self.y_prev + (1.0 - alpha) * x;
+        self.y_prev = y;
+        y
+    }
+}
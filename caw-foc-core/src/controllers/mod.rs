@@ -0,0 +1,2 @@
+pub mod lowpass_filter;
+pub mod pid;
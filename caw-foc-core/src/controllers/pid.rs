@@ -0,0 +1,83 @@
+use embassy_time::Instant;
+
+use crate::constrain;
+
+/// 按时间差计算时的周期范围(秒), 时间戳相同或长时间未调用时限幅, 避免除零和积分突变
+const MIN_SAMPLE_TIME: f32 = 1e-6;
+const MAX_SAMPLE_TIME: f32 = 0.5;
+
+pub struct PIDController {
+    pub p: f32,
+    pub i: f32,
+    pub d: f32,
+    pub output_ramp: f32, // 输出变化率限制(单位/秒), 0表示不限制
+    pub limit: f32,       // 输出限幅
+    error_prev: f32,
+    output_prev: f32,
+    integral_prev: f32,
+    timestamp_prev: u64,
+    sample_time: Option<f32>, // 固定的调用周期(秒), None时按两次调用的时间差计算
+}
+
+impl PIDController {
+    pub fn new(p: f32, i: f32, d: f32, output_ramp: f32, limit: f32) -> Self {
+        Self {
+            p,
+            i,
+            d,
+            output_ramp,
+            limit,
+            error_prev: 0.0,
+            output_prev: 0.0,
+            integral_prev: 0.0,
+            timestamp_prev: Instant::now().as_micros(),
+            sample_time: None,
+        }
+    }
+
+    /// 按固定周期调用时设置, 不再读取Instant计算时间差
+    pub fn set_sample_time(&mut self, ts: f32) {
+        self.sample_time = Some(ts);
+    }
+
+    pub fn update(&mut self, error: f32) -> f32 {
+        let now_us = Instant::now().as_micros();
+        let ts = match self.sample_time {
+            Some(ts) => ts,
+            None => ((now_us.saturating_sub(self.timestamp_prev)) as f32 * 1e-6)
+                .clamp(MIN_SAMPLE_TIME, MAX_SAMPLE_TIME),
+        };
+
+        let proportional = self.p * error;
+        // 梯形积分, 并限幅防止积分饱和
+        let integral = self.integral_prev + self.i * ts * 0.5 * (error + self.error_prev);
+        let integral = constrain!(integral, -self.limit, self.limit);
+        let derivative = self.d * (error - self.error_prev) / ts;
+
+        let mut output = proportional + integral + derivative;
+        output = constrain!(output, -self.limit, self.limit);
+
+        if self.output_ramp > 0.0 {
+            let output_rate = (output - self.output_prev) / ts;
+            if output_rate > self.output_ramp {
+                output = self.output_prev + self.output_ramp * ts;
+            } else if output_rate < -self.output_ramp {
+                output = self.output_prev - self.output_ramp * ts;
+            }
+        }
+
+        self.integral_prev = integral;
+        self.output_prev = output;
+        self.error_prev = error;
+        self.timestamp_prev = now_us;
+
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.integral_prev = 0.0;
+        self.output_prev = 0.0;
+        self.error_prev = 0.0;
+        self.timestamp_prev = Instant::now().as_micros();
+    }
+}
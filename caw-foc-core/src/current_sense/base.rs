@@ -0,0 +1,5 @@
+use crate::fast_math::transforms::PhaseCurrent;
+
+pub trait CurrentSense {
+    fn get_phase_currents(&mut self) -> PhaseCurrent;
+}
@@ -0,0 +1,19 @@
+/// 三相PWM驱动
+pub trait BaseDriver {
+    /// 输出三相电压(V), 超出[0, voltage_limit]的部分被截断
+    fn set_pwm(&mut self, ua: f32, ub: f32, uc: f32);
+    /// 输出相电压的上限(V)
+    fn voltage_limit(&self) -> f32;
+    /// 母线电压(V)
+    fn voltage_power_supply(&self) -> f32;
+    /// 更新母线电压测量值, 占空比按实测电压计算
+    fn set_voltage_power_supply(&mut self, voltage: f32);
+    /// 实际可输出的相电压上限, 不超过母线电压
+    fn output_limit(&self) -> f32 {
+        self.voltage_limit().min(self.voltage_power_supply())
+    }
+    /// 使能三相输出
+    fn enable(&mut self);
+    /// 关闭三相输出, 占空比置0
+    fn disable(&mut self);
+}
@@ -0,0 +1,55 @@
+//! 主机测试用的PWM驱动, 记录最近一次输出的三相电压
+
+use super::base::BaseDriver;
+use crate::constrain;
+
+pub struct MockDriver {
+    pub voltage_power_supply: f32,
+    pub voltage_limit: f32,
+    pub enabled: bool,
+    /// 截断后的三相电压
+    pub phase_voltage: [f32; 3],
+    /// set_pwm的调用次数
+    pub updates: u32,
+}
+
+impl MockDriver {
+    pub fn new(voltage_power_supply: f32, voltage_limit: f32) -> Self {
+        Self {
+            voltage_power_supply,
+            voltage_limit,
+            enabled: true,
+            phase_voltage: [0.0; 3],
+            updates: 0,
+        }
+    }
+}
+
+impl BaseDriver for MockDriver {
+    fn set_pwm(&mut self, ua: f32, ub: f32, uc: f32) {
+        let limit = self.output_limit();
+        self.phase_voltage = [ua, ub, uc].map(|u| constrain!(u, 0.0, limit));
+        self.updates += 1;
+    }
+
+    fn voltage_limit(&self) -> f32 {
+        self.voltage_limit
+    }
+
+    fn voltage_power_supply(&self) -> f32 {
+        self.voltage_power_supply
+    }
+
+    fn set_voltage_power_supply(&mut self, voltage: f32) {
+        self.voltage_power_supply = voltage;
+    }
+
+    fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    fn disable(&mut self) {
+        self.set_pwm(0.0, 0.0, 0.0);
+        self.enabled = false;
+    }
+}
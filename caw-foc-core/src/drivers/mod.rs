@@ -0,0 +1,3 @@
+pub mod base;
+#[cfg(test)]
+pub mod mock;
@@ -0,0 +1,417 @@
+use embedded_hal_async::{
+    delay::DelayNs,
+    spi::{self, Operation},
+};
+
+use super::{faults::FaultStatus, registers::*};
+
+/// CSA自动校准时间t_CAL的上限(us)
+pub const T_CAL_US: u32 = 100;
+/// HSR的LOCK字段, 正常应答中不会为000
+const HSR_LOCK_MASK: u16 = 0x7 << 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Drv8323Error {
+    Spi,
+    /// 回读的寄存器值与写入值不一致
+    ReadbackMismatch {
+        reg: u16,
+        wrote: u16,
+        read: u16,
+    },
+    /// SDO全为0或全为1, 芯片未响应
+    NotResponding,
+}
+
+/// CSA偏置校准方式
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CsaCalibration {
+    /// 置位CSA_CAL_A/B/C短路放大器输入, 等待t_CAL后清除
+    Manual,
+    /// 芯片在ENABLE上升沿后自动校准, 只等待t_CAL并确认CSA_CAL位已清除
+    Auto,
+}
+
+pub struct DRV8232RS<SPI, D> {
+    spi: SPI,
+    delay: D,
+}
+
+impl<SPI, D> DRV8232RS<SPI, D>
+where
+    SPI: spi::SpiDevice<u16>,
+    D: DelayNs,
+{
+    pub fn new(spi: SPI, delay: D) -> DRV8232RS<SPI, D> {
+        DRV8232RS { spi, delay }
+    }
+
+    async fn write(&mut self, val: u16) -> Result<u16, Drv8323Error> {
+        let mut rx_data = [0u16; 1];
+        self.spi
+            .transaction(&mut [Operation::Transfer(&mut rx_data, &[val])])
+            .await
+            .map_err(|_| {
+                error!("drv8323 spi transfer failed");
+                Drv8323Error::Spi
+            })?;
+        Ok(rx_data[0])
+    }
+
+    pub async fn read_fsr1(&mut self) -> Result<u16, Drv8323Error> {
+        self.read_register(FSR1).await
+    }
+
+    pub async fn read_fsr2(&mut self) -> Result<u16, Drv8323Error> {
+        self.read_register(FSR2).await
+    }
+
+    /// 读寄存器, 应答为0xFFFF或0x0000时检查SDO是否卡死
+    ///
+    /// 应答的高5位固定为0, 0xFFFF说明SDO卡高。FSR1/FSR2无故障时本来就是0, 因此应答为0时再读HSR确认:
+    /// HSR的LOCK字段只能是011或110, 读到000说明SDO卡低。
+    pub async fn read_register(&mut self, reg: u16) -> Result<u16, Drv8323Error> {
+        let raw = self.write((1u16 << 15) | (reg << 11)).await?;
+        let stuck = match raw {
+            0xFFFF => true,
+            0x0000 if reg == HSR => true,
+            0x0000 => self.write((1u16 << 15) | (HSR << 11)).await? & HSR_LOCK_MASK == 0,
+            _ => false,
+        };
+        if stuck {
+            error!("drv8323 not responding, reg {} read {:016b}", reg, raw);
+            return Err(Drv8323Error::NotResponding);
+        }
+        Ok(raw)
+    }
+
+    /// 写寄存器, 不做回读校验
+    pub async fn write_register(&mut self, reg: u16, val: u16) -> Result<(), Drv8323Error> {
+        self.write((reg << 11) | (val & DATA_MASK)).await?;
+        Ok(())
+    }
+
+    /// 写寄存器并回读校验, mask为参与比较的位
+    ///
+    /// 回读由read_register先检查SDO卡高或卡低, 否则写入0x7FF等全1的值在SDO卡高时也能通过校验。
+    pub async fn write_register_verified(
+        &mut self,
+        reg: u16,
+        val: u16,
+        mask: u16,
+    ) -> Result<(), Drv8323Error> {
+        self.write_register(reg, val).await?;
+        let read = self.read_register(reg).await? & DATA_MASK;
+        if read & mask == val & mask {
+            return Ok(());
+        }
+        error!(
+            "drv8323 readback mismatch, reg {} wrote {:011b} read {:011b}",
+            reg, val, read
+        );
+        Err(Drv8323Error::ReadbackMismatch {
+            reg,
+            wrote: val,
+            read,
+        })
+    }
+
+    pub async fn read_reg<R: Register>(&mut self) -> Result<R, Drv8323Error> {
+        Ok(R::decode(self.read_register(R::ADDR).await?))
+    }
+
+    pub async fn write_reg<R: Register>(&mut self, reg: &R) -> Result<(), Drv8323Error> {
+        let val = reg.encode();
+        debug!("write reg {}: {:011b}", R::ADDR, val);
+        self.write_register_verified(R::ADDR, val, R::VERIFY_MASK)
+            .await
+    }
+
+    /// 依次写入并校验全部控制寄存器
+    pub async fn apply(&mut self, cfg: &Drv8323Config) -> Result<(), Drv8323Error> {
+        self.write_reg(&cfg.driver_control).await?;
+        self.write_reg(&cfg.gate_drive_hs).await?;
+        self.write_reg(&cfg.gate_drive_ls).await?;
+        self.write_reg(&cfg.ocp_control).await?;
+        self.write_reg(&cfg.csa_control).await
+    }
+
+    pub async fn dbg_reg_val(&mut self) -> Result<(), Drv8323Error> {
+        let fsr1 = self.read_register(FSR1).await?;
+        self.delay.delay_us(10).await;
+        let fsr2 = self.read_register(FSR2).await?;
+        self.delay.delay_us(10).await;
+        let dcr = self.read_register(DCR).await?;
+        self.delay.delay_us(10).await;
+        let hsr = self.read_register(HSR).await?;
+        self.delay.delay_us(10).await;
+        let lsr = self.read_register(LSR).await?;
+        self.delay.delay_us(10).await;
+        let ocpcr = self.read_register(OCPCR).await?;
+        self.delay.delay_us(10).await;
+        let csacr = self.read_register(CSACR).await?;
+        self.delay.delay_us(10).await;
+        debug!(
+            "FSR1:{:016b} FSR2:{:016b} DCR:{:016b} HSR:{:016b} LSR:{:016b} OCPCR:{:016b} CSACR:{:016b}",
+            fsr1,fsr2,dcr,hsr,lsr,ocpcr,csacr
+        );
+        Ok(())
+    }
+
+    pub async fn read_faults(&mut self) -> Result<FaultStatus, Drv8323Error> {
+        let fsr1 = self.read_fsr1().await?;
+        self.delay.delay_us(10).await;
+        let fsr2 = self.read_fsr2().await?;
+        Ok(FaultStatus::decode(fsr1 & DATA_MASK, fsr2 & DATA_MASK))
+    }
+
+    /// 写CLR_FLT清除锁存的故障, 该位由芯片自动清零
+    pub async fn clear_faults(&mut self) -> Result<(), Drv8323Error> {
+        let mut dcr: DriverControl = self.read_reg().await?;
+        dcr.clr_flt = true;
+        self.write_reg(&dcr).await
+    }
+
+    /// 清除DCR的COAST位, 使能栅极驱动; COAST置位时所有MOSFET处于高阻态
+    pub async fn enable_gd(&mut self) -> Result<(), Drv8323Error> {
+        let mut dcr: DriverControl = self.read_reg().await?;
+        dcr.coast = false;
+        self.write_reg(&dcr).await
+    }
+
+    /// 置位COAST, 所有MOSFET进入高阻态
+    pub async fn disable_gd(&mut self) -> Result<(), Drv8323Error> {
+        let mut dcr: DriverControl = self.read_reg().await?;
+        dcr.coast = true;
+        self.write_reg(&dcr).await
+    }
+
+    /// 校准CSA偏置, 校准期间放大器输出无效, 需在电机静止时调用
+    ///
+    /// 只修改CSA_CAL_A/B/C位, 其余CSACR配置保持不变。
+    pub async fn calibrate_csa(&mut self, mode: CsaCalibration) -> Result<(), Drv8323Error> {
+        let mut csa: CsaControl = self.read_reg().await?;
+        if mode == CsaCalibration::Manual {
+            csa.csa_cal_a = true;
+            csa.csa_cal_b = true;
+            csa.csa_cal_c = true;
+            self.write_reg(&csa).await?;
+        }
+        self.delay.delay_us(T_CAL_US).await;
+        if csa.csa_cal_a || csa.csa_cal_b || csa.csa_cal_c {
+            csa.csa_cal_a = false;
+            csa.csa_cal_b = false;
+            csa.csa_cal_c = false;
+            self.write_reg(&csa).await?;
+        }
+        debug!("csa calibration done: {:?}", mode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drv8323::mock::{MockDelay, MockDrv8323};
+    use embassy_futures::block_on;
+
+    const CAL_BITS: u16 = 0b111 << 2;
+
+    fn driver(delay: &mut MockDelay) -> DRV8232RS<MockDrv8323, &mut MockDelay> {
+        DRV8232RS::new(MockDrv8323::new(), delay)
+    }
+
+    #[test]
+    fn read_register_frame() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        assert_eq!(block_on(drv.read_register(OCPCR)).unwrap(), 0x159);
+        assert_eq!(block_on(drv.read_fsr1()).unwrap(), 0);
+        assert_eq!(block_on(drv.read_fsr2()).unwrap(), 0);
+        assert!(drv.spi.writes.is_empty());
+    }
+
+    #[test]
+    fn write_register_masks_data() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        block_on(drv.write_register(OCPCR, 0xF123)).unwrap();
+        assert_eq!(drv.spi.reg(OCPCR), 0x123);
+    }
+
+    #[test]
+    fn typed_register_round_trip() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        let ocp = OcpControl {
+            vds_lvl: VdsLevel::V1_88,
+            ..Default::default()
+        };
+        block_on(drv.write_reg(&ocp)).unwrap();
+        assert_eq!(block_on(drv.read_reg::<OcpControl>()).unwrap(), ocp);
+    }
+
+    #[test]
+    fn apply_writes_all_registers() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        let cfg = Drv8323Config::default();
+        block_on(drv.apply(&cfg)).unwrap();
+        // CLR_FLT自动清零, 不参与比较
+        assert_eq!(
+            drv.spi.reg(DCR),
+            cfg.driver_control.encode() & DriverControl::VERIFY_MASK
+        );
+        assert_eq!(drv.spi.reg(HSR), cfg.gate_drive_hs.encode());
+        assert_eq!(drv.spi.reg(LSR), cfg.gate_drive_ls.encode());
+        assert_eq!(drv.spi.reg(OCPCR), cfg.ocp_control.encode());
+        assert_eq!(drv.spi.reg(CSACR), cfg.csa_control.encode());
+    }
+
+    #[test]
+    fn write_to_locked_device_is_detected() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        let hs = GateDriveHs {
+            lock: Lock::Locked,
+            ..Default::default()
+        };
+        block_on(drv.write_reg(&hs)).unwrap();
+        let csa = CsaControl {
+            csa_gain: CsaGain::V40,
+            ..Default::default()
+        };
+        assert_eq!(
+            block_on(drv.write_reg(&csa)),
+            Err(Drv8323Error::ReadbackMismatch {
+                reg: CSACR,
+                wrote: csa.encode(),
+                read: 0x283,
+            })
+        );
+    }
+
+    #[test]
+    fn stuck_bus_is_not_responding() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        for stuck in [0x0000, 0xFFFF] {
+            drv.spi.stuck = Some(stuck);
+            assert_eq!(
+                block_on(drv.write_reg(&OcpControl::default())),
+                Err(Drv8323Error::NotResponding)
+            );
+        }
+        // SDO卡高时回读掩码后为0x7FF, 写入LSR的复位值0x7FF也不能通过校验
+        drv.spi.stuck = Some(0xFFFF);
+        assert_eq!(
+            block_on(drv.write_register_verified(LSR, 0x7FF, DATA_MASK)),
+            Err(Drv8323Error::NotResponding)
+        );
+    }
+
+    #[test]
+    fn stuck_bus_is_detected_on_reads() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        // 无故障时FSR1/FSR2读到0, 总线正常
+        assert_eq!(block_on(drv.read_faults()).unwrap().bits(), 0);
+        for stuck in [0x0000, 0xFFFF] {
+            drv.spi.stuck = Some(stuck);
+            assert_eq!(
+                block_on(drv.read_faults()),
+                Err(Drv8323Error::NotResponding)
+            );
+            assert_eq!(
+                block_on(drv.read_reg::<DriverControl>()),
+                Err(Drv8323Error::NotResponding)
+            );
+            assert_eq!(block_on(drv.enable_gd()), Err(Drv8323Error::NotResponding));
+        }
+    }
+
+    #[test]
+    fn spi_error_is_propagated() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        drv.spi.fail = true;
+        assert_eq!(block_on(drv.read_register(DCR)), Err(Drv8323Error::Spi));
+        assert_eq!(block_on(drv.enable_gd()), Err(Drv8323Error::Spi));
+        assert_eq!(block_on(drv.read_faults()), Err(Drv8323Error::Spi));
+    }
+
+    #[test]
+    fn dbg_reg_val_reads_every_register() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        block_on(drv.dbg_reg_val()).unwrap();
+        assert!(drv.spi.writes.is_empty());
+    }
+
+    #[test]
+    fn coast_toggles_only_coast_bit() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        block_on(drv.apply(&Drv8323Config::default())).unwrap();
+        let dcr = drv.spi.reg(DCR);
+        block_on(drv.disable_gd()).unwrap();
+        assert_eq!(drv.spi.reg(DCR), dcr | (1 << 2));
+        block_on(drv.enable_gd()).unwrap();
+        assert_eq!(drv.spi.reg(DCR), dcr);
+    }
+
+    #[test]
+    fn read_and_clear_faults() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        drv.spi.inject_faults(1 << 3, (1 << 8) | (1 << 7));
+        let faults = block_on(drv.read_faults()).unwrap();
+        assert!(faults.fault);
+        assert!(faults.phases[1].vds_h);
+        assert!(faults.phases[2].sense_oc);
+        assert!(faults.otw);
+
+        block_on(drv.clear_faults()).unwrap();
+        assert!(!drv.spi.nfault());
+        let faults = block_on(drv.read_faults()).unwrap();
+        assert_eq!(faults.bits(), 1 << (7 + 11));
+    }
+
+    #[test]
+    fn manual_calibration_sets_and_clears_cal_bits() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        let csa = CsaControl {
+            csa_gain: CsaGain::V40,
+            ..Default::default()
+        };
+        block_on(drv.write_reg(&csa)).unwrap();
+        block_on(drv.calibrate_csa(CsaCalibration::Manual)).unwrap();
+
+        let writes = &drv.spi.writes;
+        assert_eq!(writes.len(), 3);
+        assert_eq!(writes[1], (CSACR, csa.encode() | CAL_BITS));
+        assert_eq!(writes[2], (CSACR, csa.encode()));
+        assert!(delay.ns >= T_CAL_US as u64 * 1000);
+    }
+
+    #[test]
+    fn auto_calibration_only_waits() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        block_on(drv.calibrate_csa(CsaCalibration::Auto)).unwrap();
+        assert!(drv.spi.writes.is_empty());
+        assert!(delay.ns >= T_CAL_US as u64 * 1000);
+    }
+
+    #[test]
+    fn auto_calibration_clears_stale_cal_bits() {
+        let mut delay = MockDelay::default();
+        let mut drv = driver(&mut delay);
+        drv.spi.regs[CSACR as usize] |= CAL_BITS;
+        block_on(drv.calibrate_csa(CsaCalibration::Auto)).unwrap();
+        assert_eq!(drv.spi.writes, [(CSACR, CsaControl::default().encode())]);
+    }
+}
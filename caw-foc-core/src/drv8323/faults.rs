@@ -0,0 +1,166 @@
+//! DRV8323RS故障状态寄存器FSR1/FSR2解码
+
+/// 单相的故障标志
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PhaseFaults {
+    pub vds_h: bool,    // 上桥臂VDS过流
+    pub vds_l: bool,    // 下桥臂VDS过流
+    pub vgs_h: bool,    // 上桥臂栅极驱动故障
+    pub vgs_l: bool,    // 下桥臂栅极驱动故障
+    pub sense_oc: bool, // 采样电阻过流
+}
+
+/// FSR1和FSR2的故障标志
+///
+/// 可以与22位的位域相互转换, 低11位为FSR1, 高11位为FSR2, 用于CAN/USART上报。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FaultStatus {
+    pub fault: bool,
+    pub vds_ocp: bool,
+    pub gdf: bool,
+    pub uvlo: bool,
+    pub otsd: bool,
+    pub otw: bool,
+    pub cpuv: bool,
+    pub phases: [PhaseFaults; 3], // A B C
+}
+
+/// 位域中各位的名称
+pub const FAULT_NAMES: [&str; 22] = [
+    "VDS_LC", "VDS_HC", "VDS_LB", "VDS_HB", "VDS_LA", "VDS_HA", "OTSD", "UVLO", "GDF", "VDS_OCP",
+    "FAULT", "VGS_LC", "VGS_HC", "VGS_LB", "VGS_HB", "VGS_LA", "VGS_HA", "CPUV", "OTW", "SC_OC",
+    "SB_OC", "SA_OC",
+];
+
+fn bit(val: u16, n: u16) -> bool {
+    val & (0x1 << n) != 0
+}
+
+impl FaultStatus {
+    pub fn decode(fsr1: u16, fsr2: u16) -> Self {
+        // 每相的VDS/VGS标志依次占用2位, A相在高位
+        let phase = |n: u16| PhaseFaults {
+            vds_h: bit(fsr1, 5 - 2 * n),
+            vds_l: bit(fsr1, 4 - 2 * n),
+            vgs_h: bit(fsr2, 5 - 2 * n),
+            vgs_l: bit(fsr2, 4 - 2 * n),
+            sense_oc: bit(fsr2, 10 - n),
+        };
+        Self {
+            fault: bit(fsr1, 10),
+            vds_ocp: bit(fsr1, 9),
+            gdf: bit(fsr1, 8),
+            uvlo: bit(fsr1, 7),
+            otsd: bit(fsr1, 6),
+            otw: bit(fsr2, 7),
+            cpuv: bit(fsr2, 6),
+            phases: [phase(0), phase(1), phase(2)],
+        }
+    }
+
+    pub fn fsr1(&self) -> u16 {
+        let mut val = ((self.fault as u16) << 10)
+            | ((self.vds_ocp as u16) << 9)
+            | ((self.gdf as u16) << 8)
+            | ((self.uvlo as u16) << 7)
+            | ((self.otsd as u16) << 6);
+        for (n, p) in self.phases.iter().enumerate() {
+            let n = n as u16;
+            val |= ((p.vds_h as u16) << (5 - 2 * n)) | ((p.vds_l as u16) << (4 - 2 * n));
+        }
+        val
+    }
+
+    pub fn fsr2(&self) -> u16 {
+        let mut val = ((self.otw as u16) << 7) | ((self.cpuv as u16) << 6);
+        for (n, p) in self.phases.iter().enumerate() {
+            let n = n as u16;
+            val |= ((p.sense_oc as u16) << (10 - n))
+                | ((p.vgs_h as u16) << (5 - 2 * n))
+                | ((p.vgs_l as u16) << (4 - 2 * n));
+        }
+        val
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.fsr1() as u32 | ((self.fsr2() as u32) << 11)
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        Self::decode((bits & 0x7FF) as u16, ((bits >> 11) & 0x7FF) as u16)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits() == 0
+    }
+
+    /// 已置位标志的名称
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        let bits = self.bits();
+        FAULT_NAMES
+            .iter()
+            .enumerate()
+            .filter(move |(n, _)| bits & (0x1 << n) != 0)
+            .map(|(_, name)| *name)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for FaultStatus {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "FaultStatus(");
+        for (n, name) in self.names().enumerate() {
+            if n > 0 {
+                defmt::write!(f, "|");
+            }
+            defmt::write!(f, "{=str}", name);
+        }
+        defmt::write!(f, ")");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip() {
+        for fsr1 in 0..=0x7FF {
+            let status = FaultStatus::decode(fsr1, 0x7FF - fsr1);
+            assert_eq!(status.fsr1(), fsr1);
+            assert_eq!(status.fsr2(), 0x7FF - fsr1);
+            assert_eq!(FaultStatus::from_bits(status.bits()), status);
+        }
+    }
+
+    #[test]
+    fn per_phase_flags() {
+        // FSR1: VDS_HA, VDS_LC
+        // FSR2: SB_OC, VGS_LB
+        let status = FaultStatus::decode((1 << 5) | 1, (1 << 9) | (1 << 2));
+        assert!(status.phases[0].vds_h);
+        assert!(!status.phases[0].vds_l);
+        assert!(status.phases[2].vds_l);
+        assert!(status.phases[1].sense_oc);
+        assert!(status.phases[1].vgs_l);
+        assert!(!status.phases[1].vgs_h);
+        assert!(!status.fault);
+    }
+
+    #[test]
+    fn global_flags() {
+        let status = FaultStatus::decode((1 << 10) | (1 << 7) | (1 << 6), (1 << 7) | (1 << 6));
+        assert!(status.fault && status.uvlo && status.otsd && status.otw && status.cpuv);
+        assert!(!status.gdf && !status.vds_ocp);
+    }
+
+    #[test]
+    fn names_match_bits() {
+        let status = FaultStatus::decode(1 << 10, 1 << 10);
+        let mut names = status.names();
+        assert_eq!(names.next(), Some("FAULT"));
+        assert_eq!(names.next(), Some("SA_OC"));
+        assert_eq!(names.next(), None);
+        assert!(FaultStatus::default().is_empty());
+    }
+}
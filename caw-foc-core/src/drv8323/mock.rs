@@ -0,0 +1,248 @@
+//! 主机测试用的DRV8323寄存器模型, 实现`SpiDevice<u16>`
+
+use embedded_hal_async::{
+    delay::DelayNs,
+    spi::{self, ErrorKind, Operation},
+};
+
+use super::registers::*;
+
+/// 数据手册中的复位值
+const RESET_VALUES: [u16; 7] = [0x000, 0x000, 0x000, 0x3FF, 0x7FF, 0x159, 0x283];
+
+/// HSR的LOCK字段, 锁定后仍可写入
+const HSR_LOCK_MASK: u16 = 0x7 << 8;
+/// DCR的CLR_FLT/BRAKE/COAST, 锁定后仍可写入
+const DCR_UNLOCKED_MASK: u16 = 0x7;
+
+const FAULT_BIT: u16 = 1 << 10;
+/// FSR2中的警告位OTW和CPUV, 不触发FAULT
+const FSR2_WARNINGS: u16 = (1 << 7) | (1 << 6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockSpiError;
+
+impl spi::Error for MockSpiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// DRV8323寄存器文件模拟器
+///
+/// 帧格式: bit15为读写位(1为读), bit14-11为地址, bit10-0为数据,
+/// 返回的SDO为该寄存器在本次操作之前的值。
+pub struct MockDrv8323 {
+    pub regs: [u16; 7],
+    /// 写入帧的记录(地址, 数据), 包括被忽略的写入
+    pub writes: Vec<(u16, u16)>,
+    /// SDO固定为该值, 模拟芯片未响应
+    pub stuck: Option<u16>,
+    /// 为true时事务返回错误
+    pub fail: bool,
+}
+
+impl Default for MockDrv8323 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDrv8323 {
+    pub fn new() -> Self {
+        Self {
+            regs: RESET_VALUES,
+            writes: Vec::new(),
+            stuck: None,
+            fail: false,
+        }
+    }
+
+    pub fn reg(&self, addr: u16) -> u16 {
+        self.regs[addr as usize]
+    }
+
+    pub fn locked(&self) -> bool {
+        (self.reg(HSR) >> 8) & 0x7 == Lock::Locked.bits()
+    }
+
+    /// nFAULT引脚是否拉低
+    pub fn nfault(&self) -> bool {
+        self.reg(FSR1) & FAULT_BIT != 0
+    }
+
+    /// 注入故障位, 除OTW/CPUV外都会置位FAULT
+    pub fn inject_faults(&mut self, fsr1: u16, fsr2: u16) {
+        self.regs[FSR1 as usize] |= fsr1 & DATA_MASK;
+        self.regs[FSR2 as usize] |= fsr2 & DATA_MASK;
+        if fsr1 & DATA_MASK != 0 || fsr2 & !FSR2_WARNINGS & DATA_MASK != 0 {
+            self.regs[FSR1 as usize] |= FAULT_BIT;
+        }
+    }
+
+    fn write(&mut self, addr: u16, val: u16) {
+        self.writes.push((addr, val));
+        let locked = self.locked();
+        let lock = (val >> 8) & 0x7;
+        // LOCK字段只接受锁定和解锁两个值
+        let lock_mask = if lock == Lock::Unlocked.bits() || (!locked && lock == Lock::Locked.bits())
+        {
+            HSR_LOCK_MASK
+        } else {
+            0
+        };
+        let mask = match addr {
+            HSR if locked => lock_mask,
+            HSR => lock_mask | 0xFF,
+            DCR if locked => DCR_UNLOCKED_MASK,
+            DCR..=CSACR if !locked => DATA_MASK,
+            // 状态寄存器只读
+            _ => 0,
+        };
+        if let Some(reg) = self.regs.get_mut(addr as usize) {
+            *reg = (*reg & !mask) | (val & mask);
+        }
+        if addr == DCR && val & mask & 0x1 != 0 {
+            // CLR_FLT清除锁存的故障后自动清零
+            self.regs[FSR1 as usize] = 0;
+            self.regs[FSR2 as usize] &= FSR2_WARNINGS;
+            self.regs[DCR as usize] &= !0x1;
+        }
+    }
+
+    fn frame(&mut self, tx: u16) -> u16 {
+        let addr = (tx >> 11) & 0xF;
+        let rx = self.regs.get(addr as usize).copied().unwrap_or(0);
+        if tx & (1 << 15) == 0 {
+            self.write(addr, tx & DATA_MASK);
+        }
+        self.stuck.unwrap_or(rx)
+    }
+}
+
+impl spi::ErrorType for MockDrv8323 {
+    type Error = MockSpiError;
+}
+
+impl spi::SpiDevice<u16> for MockDrv8323 {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u16>],
+    ) -> Result<(), MockSpiError> {
+        if self.fail {
+            return Err(MockSpiError);
+        }
+        for op in operations {
+            match op {
+                Operation::Transfer(rx, tx) => {
+                    for (r, t) in rx.iter_mut().zip(tx.iter()) {
+                        *r = self.frame(*t);
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    for w in buf.iter_mut() {
+                        *w = self.frame(*w);
+                    }
+                }
+                Operation::Write(tx) => {
+                    for t in tx.iter() {
+                        self.frame(*t);
+                    }
+                }
+                Operation::Read(rx) => {
+                    // 读操作时SDI为0, 相当于写地址0
+                    for r in rx.iter_mut() {
+                        *r = self.frame(0);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 只累计等待时间的延时
+#[derive(Debug, Default)]
+pub struct MockDelay {
+    pub ns: u64,
+}
+
+impl DelayNs for &mut MockDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.ns += ns as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embedded_hal_async::spi::SpiDevice;
+
+    fn transfer(spi: &mut MockDrv8323, tx: u16) -> u16 {
+        let mut rx = [0u16];
+        block_on(spi.transaction(&mut [Operation::Transfer(&mut rx, &[tx])])).unwrap();
+        rx[0]
+    }
+
+    #[test]
+    fn read_returns_reset_values() {
+        let mut spi = MockDrv8323::new();
+        for addr in 0..7 {
+            assert_eq!(
+                transfer(&mut spi, (1 << 15) | (addr << 11)),
+                RESET_VALUES[addr as usize]
+            );
+        }
+    }
+
+    #[test]
+    fn write_returns_previous_value() {
+        let mut spi = MockDrv8323::new();
+        assert_eq!(transfer(&mut spi, (OCPCR << 11) | 0x123), 0x159);
+        assert_eq!(spi.reg(OCPCR), 0x123);
+    }
+
+    #[test]
+    fn status_registers_are_read_only() {
+        let mut spi = MockDrv8323::new();
+        transfer(&mut spi, (FSR1 << 11) | 0x7FF);
+        assert_eq!(spi.reg(FSR1), 0);
+    }
+
+    #[test]
+    fn lock_ignores_writes() {
+        let mut spi = MockDrv8323::new();
+        transfer(&mut spi, (HSR << 11) | (Lock::Locked.bits() << 8) | 0xFF);
+        assert!(spi.locked());
+        transfer(&mut spi, CSACR << 11);
+        assert_eq!(spi.reg(CSACR), 0x283);
+        // 锁定后仍可写COAST
+        transfer(&mut spi, (DCR << 11) | 0x3FC);
+        assert_eq!(spi.reg(DCR), 0x004);
+        // 非解锁值不能解锁
+        transfer(&mut spi, (HSR << 11) | (0x1 << 8));
+        assert!(spi.locked());
+        transfer(&mut spi, (HSR << 11) | (Lock::Unlocked.bits() << 8));
+        assert!(!spi.locked());
+    }
+
+    #[test]
+    fn clear_fault_keeps_warnings() {
+        let mut spi = MockDrv8323::new();
+        spi.inject_faults(1 << 5, (1 << 7) | (1 << 10));
+        assert!(spi.nfault());
+        transfer(&mut spi, (DCR << 11) | 0x1);
+        assert!(!spi.nfault());
+        assert_eq!(spi.reg(FSR2), 1 << 7);
+        assert_eq!(spi.reg(DCR), 0);
+    }
+
+    #[test]
+    fn warnings_do_not_assert_nfault() {
+        let mut spi = MockDrv8323::new();
+        spi.inject_faults(0, FSR2_WARNINGS);
+        assert!(!spi.nfault());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod driver;
+pub mod faults;
+#[cfg(test)]
+pub mod mock;
+pub mod registers;
@@ -0,0 +1,598 @@
+//! DRV8323RS寄存器模型
+//!
+//! 每个控制寄存器对应一个结构体, 字段使用枚举表示, 通过[`Register`]编码为
+//! 写入SPI帧的11位数据, 或从读回的数据解码。
+
+pub const FSR1: u16 = 0x0;
+pub const FSR2: u16 = 0x1;
+pub const DCR: u16 = 0x2;
+pub const HSR: u16 = 0x3;
+pub const LSR: u16 = 0x4;
+pub const OCPCR: u16 = 0x5;
+pub const CSACR: u16 = 0x6;
+
+/// 寄存器数据位宽为11位
+pub const DATA_MASK: u16 = 0x7FF;
+
+pub trait Register: Sized + Copy {
+    const ADDR: u16;
+
+    /// 写入后回读校验时比较的位, 自动清零的位和保留位不参与比较
+    const VERIFY_MASK: u16 = DATA_MASK;
+
+    /// 编码为11位寄存器数据
+    fn encode(&self) -> u16;
+
+    /// 从寄存器数据解码, 忽略高于11位的部分
+    fn decode(val: u16) -> Self;
+}
+
+macro_rules! field_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($variant:ident = $value:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+        pub enum $name {
+            $($variant = $value),+
+        }
+
+        impl $name {
+            pub const fn bits(self) -> u16 {
+                self as u16
+            }
+
+            /// 字段的所有取值都有定义, bits需已移位并截取到字段宽度
+            pub fn from_bits(bits: u16) -> Self {
+                match bits {
+                    $(x if x == $value => Self::$variant,)+
+                    _ => unreachable!(),
+                }
+            }
+        }
+    };
+}
+
+field_enum! {
+    PwmMode {
+        X6 = 0x0,
+        X3 = 0x1,
+        X1 = 0x2,
+        Independent = 0x3,
+    }
+}
+
+field_enum! {
+    Pwm1xCom {
+        Synchronous = 0x0,
+        Asynchronous = 0x1,
+    }
+}
+
+field_enum! {
+    /// 栅极驱动上拉电流
+    IDriveP {
+        Ma10 = 0x0,
+        Ma30 = 0x1,
+        Ma60 = 0x2,
+        Ma80 = 0x3,
+        Ma120 = 0x4,
+        Ma140 = 0x5,
+        Ma170 = 0x6,
+        Ma190 = 0x7,
+        Ma260 = 0x8,
+        Ma330 = 0x9,
+        Ma370 = 0xA,
+        Ma440 = 0xB,
+        Ma570 = 0xC,
+        Ma680 = 0xD,
+        Ma820 = 0xE,
+        Ma1000 = 0xF,
+    }
+}
+
+field_enum! {
+    /// 栅极驱动下拉电流
+    IDriveN {
+        Ma20 = 0x0,
+        Ma60 = 0x1,
+        Ma120 = 0x2,
+        Ma160 = 0x3,
+        Ma240 = 0x4,
+        Ma280 = 0x5,
+        Ma340 = 0x6,
+        Ma380 = 0x7,
+        Ma520 = 0x8,
+        Ma660 = 0x9,
+        Ma740 = 0xA,
+        Ma880 = 0xB,
+        Ma1140 = 0xC,
+        Ma1360 = 0xD,
+        Ma1640 = 0xE,
+        Ma2000 = 0xF,
+    }
+}
+
+field_enum! {
+    TDrive {
+        Ns500 = 0x0,
+        Ns1000 = 0x1,
+        Ns2000 = 0x2,
+        Ns4000 = 0x3,
+    }
+}
+
+field_enum! {
+    TRetry {
+        Ms4 = 0x0,
+        Us50 = 0x1,
+    }
+}
+
+field_enum! {
+    DeadTime {
+        Ns50 = 0x0,
+        Ns100 = 0x1,
+        Ns200 = 0x2,
+        Ns400 = 0x3,
+    }
+}
+
+field_enum! {
+    OcpMode {
+        Latch = 0x0,
+        Retry = 0x1,
+        Report = 0x2,
+        Disabled = 0x3,
+    }
+}
+
+field_enum! {
+    OcpDeglitch {
+        Us2 = 0x0,
+        Us4 = 0x1,
+        Us6 = 0x2,
+        Us8 = 0x3,
+    }
+}
+
+field_enum! {
+    /// VDS过流检测阈值
+    VdsLevel {
+        V0_06 = 0x0,
+        V0_13 = 0x1,
+        V0_2 = 0x2,
+        V0_26 = 0x3,
+        V0_31 = 0x4,
+        V0_45 = 0x5,
+        V0_53 = 0x6,
+        V0_6 = 0x7,
+        V0_68 = 0x8,
+        V0_75 = 0x9,
+        V0_94 = 0xA,
+        V1_13 = 0xB,
+        V1_3 = 0xC,
+        V1_5 = 0xD,
+        V1_7 = 0xE,
+        V1_88 = 0xF,
+    }
+}
+
+field_enum! {
+    /// 电流采样放大倍数
+    CsaGain {
+        V5 = 0x0,
+        V10 = 0x1,
+        V20 = 0x2,
+        V40 = 0x3,
+    }
+}
+
+impl CsaGain {
+    /// 放大倍数(V/V)
+    pub fn gain(self) -> f32 {
+        match self {
+            CsaGain::V5 => 5.0,
+            CsaGain::V10 => 10.0,
+            CsaGain::V20 => 20.0,
+            CsaGain::V40 => 40.0,
+        }
+    }
+}
+
+field_enum! {
+    /// 采样电阻过流检测阈值
+    SenseLevel {
+        V0_25 = 0x0,
+        V0_5 = 0x1,
+        V0_75 = 0x2,
+        V1_0 = 0x3,
+    }
+}
+
+/// HSR的LOCK字段, 写入其他值无效
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Lock {
+    Unlocked = 0x3,
+    Locked = 0x6,
+}
+
+impl Lock {
+    pub const fn bits(self) -> u16 {
+        self as u16
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        if bits == Lock::Locked.bits() {
+            Lock::Locked
+        } else {
+            Lock::Unlocked
+        }
+    }
+}
+
+fn bit(val: u16, n: u16) -> bool {
+    val & (0x1 << n) != 0
+}
+
+/// Driver Control Register (0x02)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriverControl {
+    pub dis_cpuv: bool,
+    pub dis_gdf: bool,
+    pub otw_rep: bool,
+    pub pwm_mode: PwmMode,
+    pub pwm_1x_com: Pwm1xCom,
+    pub pwm_1x_dir: bool,
+    pub coast: bool,
+    pub brake: bool,
+    pub clr_flt: bool,
+}
+
+impl Register for DriverControl {
+    const ADDR: u16 = DCR;
+    // bit10保留, CLR_FLT写入后自动清零
+    const VERIFY_MASK: u16 = 0x3FE;
+
+    fn encode(&self) -> u16 {
+        ((self.dis_cpuv as u16) << 9)
+            | ((self.dis_gdf as u16) << 8)
+            | ((self.otw_rep as u16) << 7)
+            | (self.pwm_mode.bits() << 5)
+            | (self.pwm_1x_com.bits() << 4)
+            | ((self.pwm_1x_dir as u16) << 3)
+            | ((self.coast as u16) << 2)
+            | ((self.brake as u16) << 1)
+            | (self.clr_flt as u16)
+    }
+
+    fn decode(val: u16) -> Self {
+        Self {
+            dis_cpuv: bit(val, 9),
+            dis_gdf: bit(val, 8),
+            otw_rep: bit(val, 7),
+            pwm_mode: PwmMode::from_bits((val >> 5) & 0x3),
+            pwm_1x_com: Pwm1xCom::from_bits((val >> 4) & 0x1),
+            pwm_1x_dir: bit(val, 3),
+            coast: bit(val, 2),
+            brake: bit(val, 1),
+            clr_flt: bit(val, 0),
+        }
+    }
+}
+
+impl Default for DriverControl {
+    fn default() -> Self {
+        Self::decode(0x000)
+    }
+}
+
+/// Gate Drive HS Register (0x03)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GateDriveHs {
+    pub lock: Lock,
+    pub idrivep_hs: IDriveP,
+    pub idriven_hs: IDriveN,
+}
+
+impl Register for GateDriveHs {
+    const ADDR: u16 = HSR;
+
+    fn encode(&self) -> u16 {
+        (self.lock.bits() << 8) | (self.idrivep_hs.bits() << 4) | self.idriven_hs.bits()
+    }
+
+    fn decode(val: u16) -> Self {
+        Self {
+            lock: Lock::from_bits((val >> 8) & 0x7),
+            idrivep_hs: IDriveP::from_bits((val >> 4) & 0xF),
+            idriven_hs: IDriveN::from_bits(val & 0xF),
+        }
+    }
+}
+
+impl Default for GateDriveHs {
+    fn default() -> Self {
+        Self::decode(0x3FF)
+    }
+}
+
+/// Gate Drive LS Register (0x04)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GateDriveLs {
+    pub cbc: bool,
+    pub tdrive: TDrive,
+    pub idrivep_ls: IDriveP,
+    pub idriven_ls: IDriveN,
+}
+
+impl Register for GateDriveLs {
+    const ADDR: u16 = LSR;
+
+    fn encode(&self) -> u16 {
+        ((self.cbc as u16) << 10)
+            | (self.tdrive.bits() << 8)
+            | (self.idrivep_ls.bits() << 4)
+            | self.idriven_ls.bits()
+    }
+
+    fn decode(val: u16) -> Self {
+        Self {
+            cbc: bit(val, 10),
+            tdrive: TDrive::from_bits((val >> 8) & 0x3),
+            idrivep_ls: IDriveP::from_bits((val >> 4) & 0xF),
+            idriven_ls: IDriveN::from_bits(val & 0xF),
+        }
+    }
+}
+
+impl Default for GateDriveLs {
+    fn default() -> Self {
+        Self::decode(0x7FF)
+    }
+}
+
+/// OCP Control Register (0x05)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OcpControl {
+    pub tretry: TRetry,
+    pub dead_time: DeadTime,
+    pub ocp_mode: OcpMode,
+    pub ocp_deg: OcpDeglitch,
+    pub vds_lvl: VdsLevel,
+}
+
+impl Register for OcpControl {
+    const ADDR: u16 = OCPCR;
+
+    fn encode(&self) -> u16 {
+        (self.tretry.bits() << 10)
+            | (self.dead_time.bits() << 8)
+            | (self.ocp_mode.bits() << 6)
+            | (self.ocp_deg.bits() << 4)
+            | self.vds_lvl.bits()
+    }
+
+    fn decode(val: u16) -> Self {
+        Self {
+            tretry: TRetry::from_bits((val >> 10) & 0x1),
+            dead_time: DeadTime::from_bits((val >> 8) & 0x3),
+            ocp_mode: OcpMode::from_bits((val >> 6) & 0x3),
+            ocp_deg: OcpDeglitch::from_bits((val >> 4) & 0x3),
+            vds_lvl: VdsLevel::from_bits(val & 0xF),
+        }
+    }
+}
+
+impl Default for OcpControl {
+    fn default() -> Self {
+        Self::decode(0x159)
+    }
+}
+
+/// CSA Control Register (0x06)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CsaControl {
+    pub csa_fet: bool,
+    pub vref_div: bool,
+    pub ls_ref: bool,
+    pub csa_gain: CsaGain,
+    pub dis_sen: bool,
+    pub csa_cal_a: bool,
+    pub csa_cal_b: bool,
+    pub csa_cal_c: bool,
+    pub sen_lvl: SenseLevel,
+}
+
+impl Register for CsaControl {
+    const ADDR: u16 = CSACR;
+
+    fn encode(&self) -> u16 {
+        ((self.csa_fet as u16) << 10)
+            | ((self.vref_div as u16) << 9)
+            | ((self.ls_ref as u16) << 8)
+            | (self.csa_gain.bits() << 6)
+            | ((self.dis_sen as u16) << 5)
+            | ((self.csa_cal_a as u16) << 4)
+            | ((self.csa_cal_b as u16) << 3)
+            | ((self.csa_cal_c as u16) << 2)
+            | self.sen_lvl.bits()
+    }
+
+    fn decode(val: u16) -> Self {
+        Self {
+            csa_fet: bit(val, 10),
+            vref_div: bit(val, 9),
+            ls_ref: bit(val, 8),
+            csa_gain: CsaGain::from_bits((val >> 6) & 0x3),
+            dis_sen: bit(val, 5),
+            csa_cal_a: bit(val, 4),
+            csa_cal_b: bit(val, 3),
+            csa_cal_c: bit(val, 2),
+            sen_lvl: SenseLevel::from_bits(val & 0x3),
+        }
+    }
+}
+
+impl Default for CsaControl {
+    fn default() -> Self {
+        Self::decode(0x283)
+    }
+}
+
+/// DRV8323全部控制寄存器的配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Drv8323Config {
+    pub driver_control: DriverControl,
+    pub gate_drive_hs: GateDriveHs,
+    pub gate_drive_ls: GateDriveLs,
+    pub ocp_control: OcpControl,
+    pub csa_control: CsaControl,
+}
+
+impl Default for Drv8323Config {
+    /// CawDrive使用的配置: 3xPWM, 关闭OCP, CSA 40V/V
+    fn default() -> Self {
+        Self {
+            driver_control: DriverControl {
+                dis_gdf: true,
+                pwm_mode: PwmMode::X3,
+                clr_flt: true,
+                ..Default::default()
+            },
+            gate_drive_hs: GateDriveHs::default(),
+            gate_drive_ls: GateDriveLs::default(),
+            ocp_control: OcpControl {
+                tretry: TRetry::Us50,
+                dead_time: DeadTime::Ns50,
+                ocp_mode: OcpMode::Disabled,
+                ocp_deg: OcpDeglitch::Us8,
+                vds_lvl: VdsLevel::V1_88,
+            },
+            csa_control: CsaControl {
+                csa_fet: false,
+                vref_div: true,
+                ls_ref: false,
+                csa_gain: CsaGain::V40,
+                dis_sen: true,
+                csa_cal_a: false,
+                csa_cal_b: false,
+                csa_cal_c: false,
+                sen_lvl: SenseLevel::V1_0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip<R: Register + PartialEq + core::fmt::Debug>(mask: u16) {
+        for val in 0..=DATA_MASK {
+            let reg = R::decode(val);
+            assert_eq!(reg.encode(), val & mask, "value {:#05x}", val);
+            assert_eq!(R::decode(reg.encode()), reg);
+        }
+    }
+
+    #[test]
+    fn driver_control_round_trip() {
+        // bit10保留
+        assert_round_trip::<DriverControl>(0x3FF);
+    }
+
+    #[test]
+    fn gate_drive_ls_round_trip() {
+        assert_round_trip::<GateDriveLs>(DATA_MASK);
+    }
+
+    #[test]
+    fn ocp_control_round_trip() {
+        assert_round_trip::<OcpControl>(DATA_MASK);
+    }
+
+    #[test]
+    fn csa_control_round_trip() {
+        assert_round_trip::<CsaControl>(DATA_MASK);
+    }
+
+    #[test]
+    fn gate_drive_hs_round_trip() {
+        for lock in [Lock::Unlocked, Lock::Locked] {
+            for low in 0..=0xFF {
+                let val = (lock.bits() << 8) | low;
+                assert_eq!(GateDriveHs::decode(val).encode(), val);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_ignores_upper_bits() {
+        assert_eq!(
+            OcpControl::decode(0xF800 | 0x159),
+            OcpControl::decode(0x159)
+        );
+        assert_eq!(CsaControl::decode(0xF800 | 0x283), CsaControl::default());
+    }
+
+    #[test]
+    fn reset_values() {
+        assert_eq!(DriverControl::default().encode(), 0x000);
+        assert_eq!(GateDriveHs::default().encode(), 0x3FF);
+        assert_eq!(GateDriveLs::default().encode(), 0x7FF);
+        assert_eq!(OcpControl::default().encode(), 0x159);
+        assert_eq!(CsaControl::default().encode(), 0x283);
+    }
+
+    #[test]
+    fn cawdrive_config() {
+        let cfg = Drv8323Config::default();
+        assert_eq!(cfg.driver_control.encode(), 0x121);
+        assert_eq!(cfg.ocp_control.encode(), 0x4FF);
+        assert_eq!(cfg.csa_control.encode(), 0x2E3);
+        assert_eq!(cfg.csa_control.csa_gain.gain(), 40.0);
+    }
+
+    #[test]
+    fn verify_mask_ignores_clr_flt() {
+        let dcr = DriverControl {
+            clr_flt: true,
+            ..Default::default()
+        };
+        assert_eq!(dcr.encode() & DriverControl::VERIFY_MASK, 0);
+        assert_eq!(OcpControl::VERIFY_MASK, DATA_MASK);
+    }
+
+    #[test]
+    fn field_positions() {
+        let dcr = DriverControl {
+            pwm_mode: PwmMode::Independent,
+            ..Default::default()
+        };
+        assert_eq!(dcr.encode(), 0x3 << 5);
+
+        let ocp = OcpControl {
+            ocp_mode: OcpMode::Report,
+            ..OcpControl::decode(0)
+        };
+        assert_eq!(ocp.encode(), 0x2 << 6);
+
+        let csa = CsaControl {
+            csa_cal_b: true,
+            ..CsaControl::decode(0)
+        };
+        assert_eq!(csa.encode(), 0x1 << 3);
+    }
+}
@@ -0,0 +1,8 @@
+use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
+pub const _SQRT3_2: f32 = 0.866_025_4;
+pub const _PI_2: f32 = FRAC_PI_2;
+pub const _PI: f32 = PI;
+pub const _2PI: f32 = TAU;
+pub const _3PI_2: f32 = 3.0 * FRAC_PI_2;
+pub const _1_SQRT3: f32 = 0.577_350_26;
@@ -0,0 +1,102 @@
+use core::f32::consts::LN_2;
+
+use super::{
+    defines::{_2PI, _PI_2},
+    table::SIN_TABLE,
+};
+
+const MULTIPLIER: f32 = 81.487_33;
+
+pub fn fast_sin(mut theta: f32) -> f32 {
+    while theta < 0.0 {
+        theta += _2PI;
+    }
+    while theta >= _2PI {
+        theta -= _2PI;
+    }
+    SIN_TABLE[(MULTIPLIER * theta) as usize]
+}
+
+pub fn fast_cos(theta: f32) -> f32 {
+    fast_sin(_PI_2 - theta)
+}
+
+pub fn fast_sincos(theta: f32) -> (f32, f32) {
+    (fast_sin(theta), fast_cos(theta))
+}
+
+/// 平方根, 位运算估计初值后做两次牛顿迭代, 相对误差小于1e-6
+pub fn fast_sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1FBD_1DF5);
+    y = 0.5 * (y + x / y);
+    0.5 * (y + x / y)
+}
+
+/// 自然对数, 拆分为指数和[1, 2)内的尾数, 尾数部分用atanh级数计算, 绝对误差小于1e-6
+pub fn fast_ln(x: f32) -> f32 {
+    if x <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    let bits = x.to_bits();
+    let e = ((bits >> 23) & 0xFF) as i32 - 127;
+    let m = f32::from_bits((bits & 0x007F_FFFF) | 0x3F80_0000);
+    // ln(m) = 2 * atanh((m - 1) / (m + 1))
+    let s = (m - 1.0) / (m + 1.0);
+    let s2 = s * s;
+    let series =
+        s * (2.0 + s2 * (2.0 / 3.0 + s2 * (2.0 / 5.0 + s2 * (2.0 / 7.0 + s2 * (2.0 / 9.0)))));
+    e as f32 * LN_2 + series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sin_matches_std() {
+        for i in -2000..2000 {
+            let theta = i as f32 * 0.01;
+            assert!(
+                (fast_sin(theta) - theta.sin()).abs() < 0.02,
+                "theta {}",
+                theta
+            );
+            assert!(
+                (fast_cos(theta) - theta.cos()).abs() < 0.02,
+                "theta {}",
+                theta
+            );
+        }
+    }
+
+    #[test]
+    fn sqrt_matches_std() {
+        for i in 0..10000 {
+            let x = i as f32 * 0.37;
+            assert!(
+                (fast_sqrt(x) - x.sqrt()).abs() <= x.sqrt() * 1e-5,
+                "x {}",
+                x
+            );
+        }
+        assert_eq!(fast_sqrt(-1.0), 0.0);
+    }
+
+    #[test]
+    fn ln_matches_std() {
+        for i in 1..10000 {
+            let x = i as f32 * 0.013;
+            assert!((fast_ln(x) - x.ln()).abs() < 1e-5, "x {}", x);
+        }
+        assert_eq!(fast_ln(0.0), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn sin_at_period_edge() {
+        let theta = f32::from_bits(_2PI.to_bits() - 1);
+        assert!(fast_sin(theta).abs() < 0.02);
+    }
+}
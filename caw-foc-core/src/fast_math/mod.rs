@@ -1,3 +1,5 @@
 pub mod defines;
 pub mod math;
+pub mod modulation;
 pub mod table;
+pub mod transforms;
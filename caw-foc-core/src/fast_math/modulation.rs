@@ -0,0 +1,299 @@
+use super::{
+    defines::{_1_SQRT3, _2PI, _PI, _SQRT3_2},
+    math::{fast_sincos, fast_sqrt},
+};
+
+const _SQRT3: f32 = 2.0 * _SQRT3_2;
+
+/// 调制方式
+///
+/// 所有方式的输出都以voltage_limit/2为中点, 由驱动截断到[0, voltage_limit]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Modulation {
+    /// 正弦调制, 三相电压直接由反Park/反Clarke变换得到
+    SinePWM,
+    /// 空间矢量调制, 按扇区计算两个相邻基本矢量的作用时间
+    #[default]
+    SpaceVectorPWM,
+    /// 方波120°, 每个60°扇区两相导通, 第三相悬空
+    Trapezoid120,
+    /// 方波150°, 每个30°扇区在两相导通和三相导通之间交替
+    Trapezoid150,
+}
+
+// 方波换相表, 1/-1为该相输出+uq/-uq, 0为悬空相
+const TRAP_120_MAP: [[i8; 3]; 6] = [
+    [0, 1, -1],
+    [-1, 1, 0],
+    [-1, 0, 1],
+    [0, -1, 1],
+    [1, -1, 0],
+    [1, 0, -1],
+];
+
+const TRAP_150_MAP: [[i8; 3]; 12] = [
+    [0, 1, -1],
+    [-1, 1, -1],
+    [-1, 1, 0],
+    [-1, 1, 1],
+    [-1, 0, 1],
+    [-1, -1, 1],
+    [0, -1, 1],
+    [1, -1, 1],
+    [1, -1, 0],
+    [1, -1, -1],
+    [1, 0, -1],
+    [1, 1, -1],
+];
+
+/// 扇区k(1~6)起始边界角(k-1)*PI/3的正弦和余弦
+const SECTOR_SIN: [f32; 7] = [0.0, _SQRT3_2, _SQRT3_2, 0.0, -_SQRT3_2, -_SQRT3_2, 0.0];
+const SECTOR_COS: [f32; 7] = [1.0, 0.5, -0.5, -1.0, -0.5, 0.5, 1.0];
+
+impl Modulation {
+    /// (ud, uq)矢量幅值的上限, voltage_limit为可输出的相电压上限
+    ///
+    /// SPWM和方波的线性区为voltage_limit/2, SVPWM为voltage_limit/sqrt3;
+    /// 开启过调制时SVPWM放宽到六步换相的基波幅值2/PI*voltage_limit。
+    pub fn max_voltage(self, voltage_limit: f32, overmodulation: bool) -> f32 {
+        match self {
+            Modulation::SpaceVectorPWM if overmodulation => 2.0 / _PI * voltage_limit,
+            Modulation::SpaceVectorPWM => _1_SQRT3 * voltage_limit,
+            _ => voltage_limit / 2.0,
+        }
+    }
+}
+
+/// 圆形限幅, 保持(uq, ud)方向不变, 幅值不超过max
+pub fn limit_voltage(uq: f32, ud: f32, max: f32) -> (f32, f32) {
+    let sq = uq * uq + ud * ud;
+    if sq <= max * max {
+        return (uq, ud);
+    }
+    let scale = max / fast_sqrt(sq);
+    (uq * scale, ud * scale)
+}
+
+/// 计算三相电压
+pub fn phase_voltages(
+    modulation: Modulation,
+    uq: f32,
+    ud: f32,
+    angle_el: f32,
+    voltage_limit: f32,
+) -> [f32; 3] {
+    match modulation {
+        Modulation::SinePWM => sine(uq, ud, angle_el, voltage_limit),
+        Modulation::SpaceVectorPWM => space_vector(uq, ud, angle_el, voltage_limit),
+        Modulation::Trapezoid120 => trapezoid(&TRAP_120_MAP, uq, angle_el, voltage_limit),
+        Modulation::Trapezoid150 => trapezoid(&TRAP_150_MAP, uq, angle_el, voltage_limit),
+    }
+}
+
+/// 反Park变换, 返回(alpha, beta)
+fn inverse_park(uq: f32, ud: f32, angle_el: f32) -> (f32, f32) {
+    let (sa, ca) = fast_sincos(angle_el);
+    (ca * ud - sa * uq, sa * ud + ca * uq)
+}
+
+fn sine(uq: f32, ud: f32, angle_el: f32, voltage_limit: f32) -> [f32; 3] {
+    let (ualpha, ubeta) = inverse_park(uq, ud, angle_el);
+    let center = voltage_limit / 2.0;
+    [
+        ualpha + center,
+        -0.5 * ualpha + _SQRT3_2 * ubeta + center,
+        -0.5 * ualpha - _SQRT3_2 * ubeta + center,
+    ]
+}
+
+fn space_vector(uq: f32, ud: f32, angle_el: f32, voltage_limit: f32) -> [f32; 3] {
+    let (ualpha, ubeta) = inverse_park(uq, ud, angle_el);
+    if voltage_limit <= 0.0 {
+        return [0.0; 3];
+    }
+    // 由三个投影的符号确定扇区, 避免计算atan2
+    let n = (ubeta > 0.0) as usize
+        | (((_SQRT3_2 * ualpha - 0.5 * ubeta) > 0.0) as usize) << 1
+        | (((-_SQRT3_2 * ualpha - 0.5 * ubeta) > 0.0) as usize) << 2;
+    let sector = match n {
+        3 => 1,
+        1 => 2,
+        5 => 3,
+        4 => 4,
+        6 => 5,
+        2 => 6,
+        // 零矢量
+        _ => 1,
+    };
+    // 两个相邻基本矢量的作用时间, 以PWM周期为单位
+    let alpha = ualpha / voltage_limit;
+    let beta = ubeta / voltage_limit;
+    let t1 = _SQRT3 * (SECTOR_SIN[sector] * alpha - SECTOR_COS[sector] * beta);
+    let t2 = _SQRT3 * (SECTOR_COS[sector - 1] * beta - SECTOR_SIN[sector - 1] * alpha);
+    let (t1, t2) = if t1 + t2 > 1.0 {
+        // 过调制: 矢量超出六边形, 按比例缩回边界, 零矢量时间为0
+        (t1 / (t1 + t2), t2 / (t1 + t2))
+    } else {
+        (t1, t2)
+    };
+    // 零矢量时间平均分配到两端, 输出以voltage_limit/2为中点
+    let t0 = 1.0 - t1 - t2;
+    let h = t0 / 2.0;
+    let [ta, tb, tc] = match sector {
+        1 => [t1 + t2 + h, t2 + h, h],
+        2 => [t1 + h, t1 + t2 + h, h],
+        3 => [h, t1 + t2 + h, t2 + h],
+        4 => [h, t1 + h, t1 + t2 + h],
+        5 => [t2 + h, h, t1 + t2 + h],
+        _ => [t1 + t2 + h, h, t1 + h],
+    };
+    [ta * voltage_limit, tb * voltage_limit, tc * voltage_limit]
+}
+
+/// 方波调制, 忽略ud; 驱动不支持单相高阻, 悬空相输出中点电压
+fn trapezoid<const N: usize>(
+    map: &[[i8; 3]; N],
+    uq: f32,
+    angle_el: f32,
+    voltage_limit: f32,
+) -> [f32; 3] {
+    let mut angle = (angle_el + _PI / 6.0) % _2PI;
+    if angle < 0.0 {
+        angle += _2PI;
+    }
+    let sector = ((N as f32 * angle / _2PI) as usize).min(N - 1);
+    let center = voltage_limit / 2.0;
+    map[sector].map(|s| s as f32 * uq + center)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 原来的中点注入算法, SVPWM的结果应与之相同
+    fn min_max_injection(uq: f32, ud: f32, angle_el: f32, voltage_limit: f32) -> [f32; 3] {
+        let [ua, ub, uc] = sine(uq, ud, angle_el, 0.0);
+        let center = voltage_limit / 2.0 - (ua.max(ub).max(uc) + ua.min(ub).min(uc)) / 2.0;
+        [ua + center, ub + center, uc + center]
+    }
+
+    fn line_voltages(u: [f32; 3]) -> [f32; 3] {
+        [u[0] - u[1], u[1] - u[2], u[2] - u[0]]
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3], tol: f32, angle: f32) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < tol, "angle {} {:?} {:?}", angle, a, b);
+        }
+    }
+
+    #[test]
+    fn svpwm_matches_min_max_injection() {
+        for (uq, ud) in [(3.0, 0.0), (-2.0, 0.0), (2.0, 1.5), (0.5, -3.0)] {
+            for i in 0..720 {
+                let angle = i as f32 * _2PI / 720.0;
+                assert_close(
+                    space_vector(uq, ud, angle, 12.0),
+                    min_max_injection(uq, ud, angle, 12.0),
+                    1e-3,
+                    angle,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sine_and_svpwm_have_same_line_voltages() {
+        for i in 0..360 {
+            let angle = i as f32 * _2PI / 360.0;
+            assert_close(
+                line_voltages(phase_voltages(Modulation::SinePWM, 3.0, 1.0, angle, 12.0)),
+                line_voltages(phase_voltages(
+                    Modulation::SpaceVectorPWM,
+                    3.0,
+                    1.0,
+                    angle,
+                    12.0,
+                )),
+                1e-3,
+                angle,
+            );
+        }
+    }
+
+    #[test]
+    fn svpwm_extends_linear_range() {
+        // 幅值为voltage_limit/sqrt3时SVPWM仍在[0, voltage_limit]内, SPWM则超出
+        let uq = 12.0 * _1_SQRT3 * 0.97;
+        let mut sine_clipped = false;
+        for i in 0..360 {
+            let angle = i as f32 * _2PI / 360.0;
+            let u = space_vector(uq, 0.0, angle, 12.0);
+            assert!(u.iter().all(|x| (0.0..=12.0).contains(x)), "{:?}", u);
+            let u = sine(uq, 0.0, angle, 12.0);
+            sine_clipped |= u.iter().any(|x| !(0.0..=12.0).contains(x));
+        }
+        assert!(sine_clipped);
+    }
+
+    #[test]
+    fn overmodulated_svpwm_stays_in_range() {
+        let uq = Modulation::SpaceVectorPWM.max_voltage(12.0, true);
+        let mut peak_line = 0.0f32;
+        for i in 0..360 {
+            let angle = i as f32 * _2PI / 360.0;
+            let u = space_vector(uq, 0.0, angle, 12.0);
+            assert!(u.iter().all(|x| (-1e-4..=12.0001).contains(x)), "{:?}", u);
+            peak_line = peak_line.max(line_voltages(u)[0].abs());
+        }
+        // 线电压峰值达到母线电压
+        assert!((peak_line - 12.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn circular_limit_keeps_direction() {
+        let (uq, ud) = limit_voltage(6.0, 8.0, 5.0);
+        assert!((uq - 3.0).abs() < 1e-4 && (ud - 4.0).abs() < 1e-4);
+        assert_eq!(limit_voltage(3.0, -4.0, 5.0), (3.0, -4.0));
+        assert_eq!(
+            Modulation::SinePWM.max_voltage(12.0, true),
+            Modulation::SinePWM.max_voltage(12.0, false)
+        );
+    }
+
+    #[test]
+    fn zero_voltage_outputs_center() {
+        for m in [
+            Modulation::SinePWM,
+            Modulation::SpaceVectorPWM,
+            Modulation::Trapezoid120,
+            Modulation::Trapezoid150,
+        ] {
+            assert_close(phase_voltages(m, 0.0, 0.0, 1.0, 12.0), [6.0; 3], 1e-4, 1.0);
+        }
+    }
+
+    #[test]
+    fn trapezoid_120_has_one_floating_phase() {
+        for i in 0..360 {
+            let angle = i as f32 * _2PI / 360.0;
+            let u = phase_voltages(Modulation::Trapezoid120, 2.0, 0.0, angle, 12.0);
+            let floating = u.iter().filter(|x| (**x - 6.0).abs() < 1e-4).count();
+            assert_eq!(floating, 1, "angle {}", angle);
+            assert!((u.iter().sum::<f32>() - 18.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn trapezoid_150_alternates_two_and_three_phases() {
+        let mut counts = [0; 2];
+        for i in 0..360 {
+            let angle = (i as f32 + 0.5) * _2PI / 360.0;
+            let u = phase_voltages(Modulation::Trapezoid150, 2.0, 0.0, angle, 12.0);
+            let floating = u.iter().filter(|x| (**x - 6.0).abs() < 1e-4).count();
+            counts[floating] += 1;
+        }
+        assert_eq!(counts, [180, 180]);
+    }
+}
@@ -0,0 +1,77 @@
+use super::{defines::_1_SQRT3, math::fast_sincos};
+
+#[derive(Clone, Copy, Default, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PhaseCurrent {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DQCurrent {
+    pub d: f32,
+    pub q: f32,
+}
+
+/// Clarke变换, 返回(alpha, beta)
+pub fn clarke(current: &PhaseCurrent) -> (f32, f32) {
+    // 三相电流之和不一定为0, 先去掉共模分量
+    let mid = (current.a + current.b + current.c) / 3.0;
+    let a = current.a - mid;
+    let b = current.b - mid;
+    let alpha = a;
+    let beta = _1_SQRT3 * a + 2.0 * _1_SQRT3 * b;
+    (alpha, beta)
+}
+
+/// Park变换
+pub fn park(alpha: f32, beta: f32, angle_el: f32) -> DQCurrent {
+    let (sa, ca) = fast_sincos(angle_el);
+    DQCurrent {
+        d: alpha * ca + beta * sa,
+        q: beta * ca - alpha * sa,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fast_math::defines::{_2PI, _PI_2};
+
+    fn balanced(amplitude: f32, angle: f32) -> PhaseCurrent {
+        PhaseCurrent {
+            a: amplitude * angle.cos(),
+            b: amplitude * (angle - _2PI / 3.0).cos(),
+            c: amplitude * (angle + _2PI / 3.0).cos(),
+        }
+    }
+
+    #[test]
+    fn clarke_removes_common_mode() {
+        let mut current = balanced(2.0, 0.3);
+        let (alpha, beta) = clarke(&current);
+        current.a += 0.5;
+        current.b += 0.5;
+        current.c += 0.5;
+        let (alpha2, beta2) = clarke(&current);
+        assert!((alpha - alpha2).abs() < 1e-5);
+        assert!((beta - beta2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn park_aligned_current_is_d_axis() {
+        for i in 0..100 {
+            let angle = i as f32 * 0.06;
+            let (alpha, beta) = clarke(&balanced(2.0, angle));
+            let dq = park(alpha, beta, angle);
+            assert!((dq.d - 2.0).abs() < 0.05, "angle {}", angle);
+            assert!(dq.q.abs() < 0.05, "angle {}", angle);
+
+            let (alpha, beta) = clarke(&balanced(1.0, angle + _PI_2));
+            let dq = park(alpha, beta, angle);
+            assert!((dq.q - 1.0).abs() < 0.05, "angle {}", angle);
+        }
+    }
+}
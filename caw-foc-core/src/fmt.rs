@@ -0,0 +1,39 @@
+//! 日志宏, 开启defmt特性时转发到defmt, 否则只做格式检查
+
+#![allow(unused_macros)]
+
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        let _ = ::core::format_args!($($arg)*);
+    }};
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::info!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        let _ = ::core::format_args!($($arg)*);
+    }};
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::warn!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        let _ = ::core::format_args!($($arg)*);
+    }};
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::error!($($arg)*);
+        #[cfg(not(feature = "defmt"))]
+        let _ = ::core::format_args!($($arg)*);
+    }};
+}
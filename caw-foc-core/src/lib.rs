@@ -0,0 +1,19 @@
+#![cfg_attr(not(test), no_std)]
+
+#[macro_use]
+mod fmt;
+mod macros;
+
+pub mod axis;
+pub mod comm;
+pub mod config;
+pub mod controllers;
+pub mod current_sense;
+pub mod drivers;
+pub mod drv8323;
+pub mod fast_math;
+pub mod monitor;
+pub mod motor;
+pub mod protection;
+pub mod sensors;
+pub mod timing;
@@ -0,0 +1,211 @@
+//! 母线电压和温度测量的换算与滤波
+
+use crate::fast_math::math::fast_ln;
+
+/// 0°C对应的开尔文温度
+const KELVIN: f32 = 273.15;
+
+/// NTC电压在参考电压1%以内时视为短路或开路
+const NTC_RAIL_MARGIN: f32 = 0.01;
+
+/// STM32G4内部温度传感器校准值对应的温度和参考电压
+const TS_CAL1_TEMP: f32 = 30.0;
+const TS_CAL2_TEMP: f32 = 130.0;
+const TS_CAL_VREF: f32 = 3.0;
+
+/// 板上VBUS分压电阻和功率管NTC的参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MonitorConfig {
+    pub adc_vref: f32,      // ADC参考电压(V)
+    pub vbus_r_top: f32,    // VBUS分压上臂(Ω)
+    pub vbus_r_bottom: f32, // VBUS分压下臂(Ω)
+    pub ntc_r_pullup: f32,  // NTC上拉电阻(Ω), NTC另一端接地
+    pub ntc_r25: f32,       // NTC在25°C时的阻值(Ω)
+    pub ntc_beta: f32,      // NTC的B值(K)
+    pub filter_tf: f32,     // 低通滤波时间常数(秒)
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            adc_vref: 3.3,
+            vbus_r_top: 47_000.0,
+            vbus_r_bottom: 3_300.0,
+            ntc_r_pullup: 10_000.0,
+            ntc_r25: 10_000.0,
+            ntc_beta: 3380.0,
+            filter_tf: 0.01,
+        }
+    }
+}
+
+impl MonitorConfig {
+    /// 分压后的ADC引脚电压换算为母线电压(V)
+    pub fn vbus(&self, voltage: f32) -> f32 {
+        voltage * (self.vbus_r_top + self.vbus_r_bottom) / self.vbus_r_bottom
+    }
+
+    /// NTC分压电压换算为温度(°C), 短路或开路时返回None
+    pub fn ntc_temperature(&self, voltage: f32) -> Option<f32> {
+        let margin = self.adc_vref * NTC_RAIL_MARGIN;
+        if voltage < margin || voltage > self.adc_vref - margin {
+            return None;
+        }
+        let r = self.ntc_r_pullup * voltage / (self.adc_vref - voltage);
+        // B值方程: 1/T = 1/T25 + ln(R/R25)/B
+        let t = 1.0 / (1.0 / (25.0 + KELVIN) + fast_ln(r / self.ntc_r25) / self.ntc_beta);
+        Some(t - KELVIN)
+    }
+}
+
+/// STM32G4内部温度传感器的原始值换算为温度(°C)
+///
+/// ts_cal1/ts_cal2为出厂时在30°C/130°C、3.0V参考电压下测得的值,
+/// adc_vref为实际的ADC参考电压。
+pub fn internal_temperature(raw: u16, adc_vref: f32, ts_cal1: u16, ts_cal2: u16) -> f32 {
+    let raw = raw as f32 * adc_vref / TS_CAL_VREF;
+    (TS_CAL2_TEMP - TS_CAL1_TEMP) / (ts_cal2 as f32 - ts_cal1 as f32) * (raw - ts_cal1 as f32)
+        + TS_CAL1_TEMP
+}
+
+/// 滤波后的测量值
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Readings {
+    pub vbus: f32,            // 母线电压(V)
+    pub fet_temperature: f32, // 功率管NTC温度(°C)
+    pub mcu_temperature: f32, // MCU内部温度(°C)
+    pub ntc_fault: bool,      // NTC短路或开路, fet_temperature保持最后的有效值
+}
+
+/// 固定周期采样的母线电压和温度监测
+pub struct PowerMonitor {
+    pub config: MonitorConfig,
+    alpha: f32,
+    readings: Readings,
+    initialized: bool,
+}
+
+impl PowerMonitor {
+    /// sample_period为调用update的周期(秒)
+    pub fn new(config: MonitorConfig, sample_period: f32) -> Self {
+        Self {
+            alpha: sample_period / (config.filter_tf + sample_period),
+            config,
+            readings: Readings::default(),
+            initialized: false,
+        }
+    }
+
+    /// 输入VBUS和NTC的ADC引脚电压(V)以及MCU内部温度(°C), 返回滤波后的测量值
+    pub fn update(
+        &mut self,
+        vbus_voltage: f32,
+        ntc_voltage: f32,
+        mcu_temperature: f32,
+    ) -> Readings {
+        let vbus = self.config.vbus(vbus_voltage);
+        let fet = self.config.ntc_temperature(ntc_voltage);
+        if fet.is_none() && !self.readings.ntc_fault {
+            warn!("ntc voltage out of range: {:?}", ntc_voltage);
+        }
+
+        let r = &mut self.readings;
+        if !self.initialized {
+            // 第一次采样直接作为初值
+            r.vbus = vbus;
+            r.fet_temperature = fet.unwrap_or(0.0);
+            r.mcu_temperature = mcu_temperature;
+            self.initialized = true;
+        } else {
+            let alpha = self.alpha;
+            r.vbus += alpha * (vbus - r.vbus);
+            if let Some(fet) = fet {
+                r.fet_temperature += alpha * (fet - r.fet_temperature);
+            }
+            r.mcu_temperature += alpha * (mcu_temperature - r.mcu_temperature);
+        }
+        r.ntc_fault = fet.is_none();
+
+        *r
+    }
+
+    pub fn readings(&self) -> Readings {
+        self.readings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 给定温度下NTC分压的电压
+    fn ntc_voltage(cfg: &MonitorConfig, temp: f32) -> f32 {
+        let t = temp + KELVIN;
+        let r = cfg.ntc_r25 * (cfg.ntc_beta * (1.0 / t - 1.0 / (25.0 + KELVIN))).exp();
+        cfg.adc_vref * r / (cfg.ntc_r_pullup + r)
+    }
+
+    #[test]
+    fn vbus_divider() {
+        let cfg = MonitorConfig::default();
+        assert!((cfg.vbus(1.64) - 24.99).abs() < 0.01);
+        assert_eq!(cfg.vbus(0.0), 0.0);
+    }
+
+    #[test]
+    fn ntc_temperature_matches_beta_equation() {
+        let cfg = MonitorConfig::default();
+        assert!((cfg.ntc_temperature(1.65).unwrap() - 25.0).abs() < 1e-3);
+        for temp in [-20.0, 0.0, 60.0, 100.0, 125.0] {
+            let t = cfg.ntc_temperature(ntc_voltage(&cfg, temp)).unwrap();
+            assert!((t - temp).abs() < 0.05, "{} {}", temp, t);
+        }
+    }
+
+    #[test]
+    fn ntc_open_or_short_is_rejected() {
+        let cfg = MonitorConfig::default();
+        assert_eq!(cfg.ntc_temperature(0.0), None);
+        assert_eq!(cfg.ntc_temperature(3.3), None);
+    }
+
+    #[test]
+    fn internal_temperature_calibration_points() {
+        // 参考电压为3.0V时直接等于校准值
+        assert!((internal_temperature(1000, 3.0, 1000, 1300) - 30.0).abs() < 1e-3);
+        assert!((internal_temperature(1300, 3.0, 1000, 1300) - 130.0).abs() < 1e-3);
+        // 3.3V参考电压下原始值偏小
+        let raw = (1150.0 * 3.0 / 3.3) as u16;
+        assert!((internal_temperature(raw, 3.3, 1000, 1300) - 80.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn filter_starts_at_first_sample_and_converges() {
+        let cfg = MonitorConfig::default();
+        let mut m = PowerMonitor::new(cfg, 1e-3);
+        let r = m.update(1.0, 1.65, 40.0);
+        assert!((r.vbus - cfg.vbus(1.0)).abs() < 1e-4);
+        assert!((r.fet_temperature - 25.0).abs() < 1e-3);
+
+        // 母线电压跌落, 一个采样周期内只变化一部分
+        let r = m.update(0.5, 1.65, 40.0);
+        assert!(r.vbus > cfg.vbus(0.5) + 1.0);
+        for _ in 0..200 {
+            m.update(0.5, 1.65, 40.0);
+        }
+        assert!((m.readings().vbus - cfg.vbus(0.5)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ntc_fault_keeps_last_temperature() {
+        let cfg = MonitorConfig::default();
+        let mut m = PowerMonitor::new(cfg, 1e-3);
+        m.update(1.0, ntc_voltage(&cfg, 60.0), 40.0);
+        let r = m.update(1.0, 3.3, 40.0);
+        assert!(r.ntc_fault);
+        assert!((r.fet_temperature - 60.0).abs() < 0.05);
+        assert!(!m.update(1.0, ntc_voltage(&cfg, 60.0), 40.0).ntc_fault);
+    }
+}
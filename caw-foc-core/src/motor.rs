@@ -0,0 +1,879 @@
+#![allow(unused)]
+
+use embassy_time::{Instant, Timer};
+
+use crate::{
+    constrain,
+    controllers::{lowpass_filter::LowPassFilter, pid::PIDController},
+    drivers::base::BaseDriver,
+    fast_math::{
+        defines::{_2PI, _3PI_2, _PI},
+        modulation::{limit_voltage, phase_voltages, Modulation},
+        transforms::{clarke, park, DQCurrent, PhaseCurrent},
+    },
+    sensors::base::{Sensor, SensorError},
+    timing::{CycleCounter, Profile},
+};
+// 对齐时判断电机是否转动的最小机械角度
+const MIN_ANGLE_DETECT_MOVEMENT: f32 = _2PI / 101.0;
+// 对齐扫描一个电周期的步数
+const ALIGN_SWEEP_STEPS: u32 = 500;
+// 对齐中较长的等待按该间隔(ms)拆分, 每段之间检查是否中止
+const ALIGN_CHECK_MS: u64 = 2;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlignError {
+    Sensor(SensorError),
+    /// 扫描一个电周期后传感器角度几乎没有变化
+    NoMovement,
+    /// 实测的机械行程与设置的极对数不符, estimated为估算的极对数
+    PolePairMismatch {
+        estimated: u32,
+    },
+    /// abort返回true, 对齐被中止
+    Aborted,
+}
+
+impl From<SensorError> for AlignError {
+    fn from(e: SensorError) -> Self {
+        AlignError::Sensor(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AlignResult {
+    pub sensor_direction: i32,
+    pub estimated_pole_pairs: u32,
+    pub zero_electric_angle: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ControlType {
+    None,
+    VelocityOpenLoop,
+    Velocity,
+    Angle,
+    Torque,
+    AngleOpenLoop,
+    FocCurrent,
+}
+
+pub struct Motor<D: BaseDriver, S: Sensor> {
+    pole_pairs: u32,
+    pub driver: D,
+    pub sensor: S,
+    open_loop_timestamp: u64,
+    voltage_sensor_align: f32,
+    zero_electric_angle: f32,
+    sensor_direction: i32,
+    shaft_velocity: f32,
+    shaft_angle: f32,
+    control_type: ControlType,
+    pub modulation: Modulation,
+    pub overmodulation: bool, // 仅SVPWM有效
+    enabled: bool,
+    derating: f32,            // 输出电压降额比例, 0~1
+    velocity_limit: f32,      // 角度模式下的速度限制(rad/s)
+    loop_period: Option<f32>, // 固定的控制周期(秒)
+    velocity_decimation: u32, // 速度环和位置环的分频系数
+    outer_count: u32,
+    outer_uq: f32,       // 速度环最近一次的输出
+    outer_velocity: f32, // 位置环最近一次的输出
+    angle_advance: f32,  // 距上次读取传感器按速度推算的机械角度增量(传感器方向)
+    cycle_counter: Option<CycleCounter>,
+    pub profile: Profile, // 设置cycle_counter后统计的执行时间
+    pub pid_velocity: PIDController,
+    pub lpf_velocity: LowPassFilter,
+    pub pid_angle: PIDController,
+    phase_current: PhaseCurrent, // 最近一次采样的相电流(A)
+    current: DQCurrent,          // 滤波后的dq轴电流(A)
+    pub pid_current_d: PIDController,
+    pub pid_current_q: PIDController,
+    pub lpf_current_d: LowPassFilter,
+    pub lpf_current_q: LowPassFilter,
+}
+
+impl<D: BaseDriver, S: Sensor> Motor<D, S> {
+    pub fn new(
+        pole_pairs: u32,
+        sensor_direction: i32,
+        mut driver: D,
+        sensor: S,
+        control_type: ControlType,
+    ) -> Self {
+        // 创建后保持关闭, 由enable打开输出
+        driver.disable();
+        let voltage_limit = driver.voltage_limit();
+        let velocity_limit = 20.0;
+        Self {
+            pole_pairs,
+            sensor_direction,
+            driver,
+            sensor,
+            open_loop_timestamp: Instant::now().as_micros(),
+            voltage_sensor_align: 3.0,
+            zero_electric_angle: 0.0,
+            shaft_velocity: 0.0,
+            shaft_angle: 0.0,
+            control_type,
+            modulation: Modulation::default(),
+            overmodulation: false,
+            enabled: false,
+            derating: 1.0,
+            loop_period: None,
+            velocity_decimation: 1,
+            outer_count: 0,
+            outer_uq: 0.0,
+            outer_velocity: 0.0,
+            angle_advance: 0.0,
+            cycle_counter: None,
+            profile: Profile::new(),
+            velocity_limit,
+            pid_velocity: PIDController::new(0.5, 10.0, 0.0, 1000.0, voltage_limit),
+            lpf_velocity: LowPassFilter::new(0.005),
+            pid_angle: PIDController::new(20.0, 0.0, 0.0, 0.0, velocity_limit),
+            phase_current: PhaseCurrent::default(),
+            current: DQCurrent::default(),
+            pid_current_d: PIDController::new(3.0, 300.0, 0.0, 0.0, voltage_limit),
+            pid_current_q: PIDController::new(3.0, 300.0, 0.0, 0.0, voltage_limit),
+            lpf_current_d: LowPassFilter::new(0.005),
+            lpf_current_q: LowPassFilter::new(0.005),
+        }
+    }
+
+    fn set_phase_voltage(&mut self, uq: f32, ud: f32, angle_el: f32) {
+        let start = self.cycles();
+        let limit = self.driver.output_limit();
+        let max = self.modulation.max_voltage(limit, self.overmodulation) * self.derating;
+        let (uq, ud) = limit_voltage(uq, ud, max);
+        let [ua, ub, uc] = phase_voltages(self.modulation, uq, ud, angle_el, limit);
+        self.driver.set_pwm(ua, ub, uc);
+        if let (Some(start), Some(end)) = (start, self.cycles()) {
+            self.profile
+                .set_phase_voltage
+                .record(end.wrapping_sub(start));
+        }
+    }
+
+    /// 读取周期计数器, 未设置时返回None
+    fn cycles(&self) -> Option<u32> {
+        self.cycle_counter.map(|counter| counter())
+    }
+
+    /// 开环模式的时间步长, 设置了loop_period时使用固定周期
+    fn open_loop_ts(&mut self) -> f32 {
+        let now_us: u64 = Instant::now().as_micros();
+        let ts = (now_us - self.open_loop_timestamp) as f32 * 1e-6;
+        self.open_loop_timestamp = now_us;
+        match self.loop_period {
+            Some(period) => period,
+            None if ts <= 0.0 || ts > 0.5 => 1e-3,
+            None => ts,
+        }
+    }
+
+    fn velocity_open_loop(&mut self, target: f32) -> f32 {
+        let ts = self.open_loop_ts();
+        self.shaft_angle = self.normalize_angle(self.shaft_angle + target * ts);
+        self.shaft_velocity = target;
+        let uq = self.driver.output_limit();
+        self.set_phase_voltage(uq, 0.0, self.electrical_angle());
+
+        uq
+    }
+
+    fn angle_open_loop(&mut self, target: f32) -> f32 {
+        let ts = self.open_loop_ts();
+        // 以不超过velocity_limit的速度逼近目标角度
+        let error = target - self.shaft_angle;
+        if error.abs() > self.velocity_limit * ts {
+            let direction = if error > 0.0 { 1.0 } else { -1.0 };
+            self.shaft_angle += direction * self.velocity_limit * ts;
+            self.shaft_velocity = direction * self.velocity_limit;
+        } else {
+            self.shaft_angle = target;
+            self.shaft_velocity = 0.0;
+        }
+        let uq = self.driver.output_limit();
+        self.set_phase_voltage(uq, 0.0, self.electrical_angle());
+
+        uq
+    }
+
+    fn torque(&mut self, target: f32) -> f32 {
+        let uq = constrain!(
+            target,
+            -self.driver.output_limit(),
+            self.driver.output_limit()
+        );
+        self.set_phase_voltage(uq, 0.0, self.sensor_electrical_angle());
+
+        uq
+    }
+
+    fn foc_current(&mut self, target: f32) -> (f32, f32) {
+        let angle_el = self.sensor_electrical_angle();
+        let (alpha, beta) = clarke(&self.phase_current);
+        let current = park(alpha, beta, angle_el);
+        self.current = DQCurrent {
+            d: self.lpf_current_d.update(current.d),
+            q: self.lpf_current_q.update(current.q),
+        };
+        // target为q轴电流(A), d轴电流控制为0
+        let uq = self.pid_current_q.update(target - self.current.q);
+        let ud = self.pid_current_d.update(-self.current.d);
+        self.set_phase_voltage(uq, ud, angle_el);
+
+        (uq, ud)
+    }
+
+    /// 速度环每velocity_decimation次step更新一次, 其余周期沿用上次的输出, 只更新电角度
+    fn velocity_closed_loop(&mut self, target: f32) -> f32 {
+        if self.outer_count == 0 {
+            self.outer_uq = self.pid_velocity.update(target - self.shaft_velocity);
+        }
+        self.set_phase_voltage(self.outer_uq, 0.0, self.sensor_electrical_angle());
+
+        self.outer_uq
+    }
+
+    fn angle_closed_loop(&mut self, target: f32) -> f32 {
+        // 多圈累计角度较大时f32精度不足, 误差用f64计算
+        let angle = self.sensor_direction as f64 * self.sensor.get_precise_angle();
+        let error = (target as f64 - angle) as f32;
+        // 位置环输出作为速度环目标, 由pid_angle.limit限制在velocity_limit以内
+        if self.outer_count == 0 {
+            self.outer_velocity = self.pid_angle.update(error);
+        }
+        self.velocity_closed_loop(self.outer_velocity)
+    }
+
+    /// 读取位置传感器, 更新轴角度和滤波后的轴速度
+    async fn update_sensor(&mut self) -> Result<(), SensorError> {
+        self.sensor.update().await?;
+        let direction = self.sensor_direction as f32;
+        self.shaft_angle = direction * self.sensor.get_angle();
+        self.shaft_velocity = self
+            .lpf_velocity
+            .update(direction * self.sensor.get_velocity());
+        Ok(())
+    }
+
+    pub async fn init(&mut self) -> Result<(), SensorError> {
+        self.sensor.init().await
+    }
+
+    fn reset_controllers(&mut self) {
+        self.pid_velocity.reset();
+        self.pid_angle.reset();
+        self.pid_current_d.reset();
+        self.pid_current_q.reset();
+        self.outer_count = 0;
+        self.angle_advance = 0.0;
+    }
+
+    /// 按loop_period设置PID和低通滤波的固定周期, 速度环和位置环的周期乘以分频系数
+    fn apply_loop_period(&mut self) {
+        let Some(period) = self.loop_period else {
+            return;
+        };
+        let outer = period * self.velocity_decimation as f32;
+        self.pid_current_d.set_sample_time(period);
+        self.pid_current_q.set_sample_time(period);
+        self.lpf_current_d.set_sample_time(period);
+        self.lpf_current_q.set_sample_time(period);
+        self.pid_velocity.set_sample_time(outer);
+        self.pid_angle.set_sample_time(outer);
+        self.lpf_velocity.set_sample_time(outer);
+    }
+
+    /// 使能驱动输出, 清除控制器状态
+    pub fn enable(&mut self) {
+        self.reset_controllers();
+        self.driver.enable();
+        self.enabled = true;
+    }
+
+    /// 输出零电压并关闭驱动, 之后step不再输出
+    pub fn disable(&mut self) {
+        self.set_phase_voltage(0.0, 0.0, 0.0);
+        self.driver.disable();
+        self.enabled = false;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 切换控制模式, 清除控制器状态
+    pub fn set_control_type(&mut self, control_type: ControlType) {
+        if self.control_type != control_type {
+            self.reset_controllers();
+            self.control_type = control_type;
+        }
+    }
+
+    /// 由固定频率的中断驱动step时设置控制周期(秒), 开环模式不再依赖时间戳
+    pub fn set_loop_period(&mut self, period: f32) {
+        self.loop_period = Some(period);
+        self.apply_loop_period();
+    }
+
+    /// 速度环、位置环和传感器读取每n次step执行一次, 电流环每次都更新
+    ///
+    /// 未读取传感器的周期按最近一次测得的速度和loop_period推算电角度。
+    pub fn set_velocity_decimation(&mut self, n: u32) {
+        self.velocity_decimation = n.max(1);
+        self.outer_count = 0;
+        self.apply_loop_period();
+    }
+
+    /// 设置周期计数器后统计step和set_phase_voltage的执行时间
+    pub fn set_cycle_counter(&mut self, counter: CycleCounter) {
+        self.cycle_counter = Some(counter);
+        self.profile.reset();
+    }
+
+    pub fn control_type(&self) -> ControlType {
+        self.control_type
+    }
+
+    /// 输入电流传感器测得的三相电流(A)
+    pub fn update_phase_current(&mut self, current: PhaseCurrent) {
+        self.phase_current = current;
+    }
+
+    /// 按比例降低输出电压的上限, 由保护管理器在过温或过载时设置
+    pub fn set_derating(&mut self, scale: f32) {
+        self.derating = constrain!(scale, 0.0, 1.0);
+    }
+
+    /// 输入实测的母线电压(V)
+    pub fn set_bus_voltage(&mut self, voltage: f32) {
+        self.driver.set_voltage_power_supply(voltage);
+    }
+
+    pub fn dq_current(&self) -> DQCurrent {
+        self.current
+    }
+
+    pub fn electrical_angle(&self) -> f32 {
+        self.shaft_angle * self.pole_pairs as f32
+    }
+
+    /// 由传感器角度计算的电角度
+    pub fn sensor_electrical_angle(&self) -> f32 {
+        self.normalize_angle(
+            self.sensor_direction as f32
+                * self.pole_pairs as f32
+                * (self.sensor.get_mechanical_angle() + self.angle_advance)
+                - self.zero_electric_angle,
+        )
+    }
+
+    pub fn normalize_angle(&self, angle: f32) -> f32 {
+        let a = angle % _2PI;
+        if a >= 0.0 {
+            a
+        } else {
+            a + _2PI
+        }
+    }
+
+    pub fn shaft_angle(&self) -> f32 {
+        self.shaft_angle
+    }
+
+    pub fn shaft_velocity(&self) -> f32 {
+        self.shaft_velocity
+    }
+
+    /// 使用已保存的对齐结果, 跳过align_sensor
+    pub fn set_sensor_alignment(&mut self, sensor_direction: i32, zero_electric_angle: f32) {
+        self.sensor_direction = sensor_direction;
+        self.zero_electric_angle = zero_electric_angle;
+    }
+
+    pub fn set_velocity_limit(&mut self, velocity_limit: f32) {
+        self.velocity_limit = velocity_limit;
+        self.pid_angle.limit = velocity_limit;
+    }
+
+    pub async fn step(&mut self, new_target: f32) {
+        if !self.enabled {
+            return;
+        }
+        let start = self.cycles();
+        self.step_inner(new_target).await;
+        if let (Some(start), Some(end)) = (start, self.cycles()) {
+            self.profile.step.record(end.wrapping_sub(start));
+        }
+    }
+
+    async fn step_inner(&mut self, new_target: f32) {
+        match self.control_type {
+            ControlType::None | ControlType::VelocityOpenLoop | ControlType::AngleOpenLoop => (),
+            // 读取失败(如共享总线被占用)时不等待, 沿用上一次的角度和速度继续外推
+            _ => {
+                if self.outer_count == 0 && self.update_sensor().await.is_ok() {
+                    self.angle_advance = 0.0;
+                } else if let Some(period) = self.loop_period {
+                    self.angle_advance += self.sensor.get_velocity() * period;
+                }
+            }
+        }
+
+        match self.control_type {
+            ControlType::VelocityOpenLoop => {
+                self.velocity_open_loop(new_target);
+            }
+            ControlType::Velocity => {
+                self.velocity_closed_loop(new_target);
+            }
+            ControlType::Angle => {
+                self.angle_closed_loop(new_target);
+            }
+            ControlType::Torque => {
+                self.torque(new_target);
+            }
+            ControlType::AngleOpenLoop => {
+                self.angle_open_loop(new_target);
+            }
+            ControlType::FocCurrent => {
+                self.foc_current(new_target);
+            }
+            _ => (),
+        }
+        self.outer_count = (self.outer_count + 1) % self.velocity_decimation;
+    }
+
+    /// 传感器对齐: 正反扫描一个电周期判断传感器方向并校验极对数, 然后测量零电角度
+    pub async fn align_sensor(&mut self) -> Result<AlignResult, AlignError> {
+        self.align_sensor_with(|| false).await
+    }
+
+    /// 与align_sensor相同, 每一步扫描和每ALIGN_CHECK_MS的等待之间调用abort,
+    /// 返回true时立即输出零电压并返回AlignError::Aborted, 用于在对齐期间评估保护
+    pub async fn align_sensor_with(
+        &mut self,
+        mut abort: impl FnMut() -> bool,
+    ) -> Result<AlignResult, AlignError> {
+        // 清除运行时按速度推算的角度增量, 对齐只使用传感器原始角度
+        self.reset_controllers();
+        let result = self.align_sensor_inner(&mut abort).await;
+        self.set_phase_voltage(0.0, 0.0, 0.0);
+        if matches!(result, Err(AlignError::Aborted)) {
+            return result;
+        }
+        Timer::after_millis(200).await;
+        if let Ok(r) = result {
+            debug!("align_sensor: {:?}", r);
+        }
+        result
+    }
+
+    /// 对齐过程中的等待, 按ALIGN_CHECK_MS拆分并检查abort
+    async fn align_delay(ms: u64, abort: &mut impl FnMut() -> bool) -> Result<(), AlignError> {
+        let mut left = ms;
+        loop {
+            if abort() {
+                return Err(AlignError::Aborted);
+            }
+            if left == 0 {
+                return Ok(());
+            }
+            let step = left.min(ALIGN_CHECK_MS);
+            Timer::after_millis(step).await;
+            left -= step;
+        }
+    }
+
+    async fn align_sensor_inner(
+        &mut self,
+        abort: &mut impl FnMut() -> bool,
+    ) -> Result<AlignResult, AlignError> {
+        // 正向扫描一个电周期
+        for i in 0..=ALIGN_SWEEP_STEPS {
+            let angle = _3PI_2 + _2PI * i as f32 / ALIGN_SWEEP_STEPS as f32;
+            self.set_phase_voltage(self.voltage_sensor_align, 0.0, angle);
+            self.sensor.update().await?;
+            Self::align_delay(2, abort).await?;
+        }
+        self.sensor.update().await?;
+        let mid_angle = self.sensor.get_angle();
+        // 反向扫描回到起点
+        for i in (0..=ALIGN_SWEEP_STEPS).rev() {
+            let angle = _3PI_2 + _2PI * i as f32 / ALIGN_SWEEP_STEPS as f32;
+            self.set_phase_voltage(self.voltage_sensor_align, 0.0, angle);
+            self.sensor.update().await?;
+            Self::align_delay(2, abort).await?;
+        }
+        self.sensor.update().await?;
+        let end_angle = self.sensor.get_angle();
+        self.set_phase_voltage(0.0, 0.0, 0.0);
+        Self::align_delay(200, abort).await?;
+
+        let moved = (mid_angle - end_angle).abs();
+        if moved < MIN_ANGLE_DETECT_MOVEMENT {
+            return Err(AlignError::NoMovement);
+        }
+        // 结果先保存在局部变量, 校验全部通过后才写入, 失败时保留原来的对齐参数
+        let sensor_direction = if mid_angle < end_angle { -1 } else { 1 };
+
+        // 一个电周期对应的机械角度为 2PI / pole_pairs
+        let estimated_pole_pairs = (_2PI / moved + 0.5) as u32;
+        if (moved * self.pole_pairs as f32 - _2PI).abs() > 0.5 {
+            return Err(AlignError::PolePairMismatch {
+                estimated: estimated_pole_pairs,
+            });
+        }
+
+        // 在电角度_3PI_2处施加uq, 转子停在电角度0, 此时读到的电角度即为零点
+        self.set_phase_voltage(self.voltage_sensor_align, 0.0, _3PI_2);
+        Self::align_delay(700, abort).await?;
+        self.sensor.update().await?;
+        let zero_electric_angle = self.normalize_angle(
+            sensor_direction as f32 * self.pole_pairs as f32 * self.sensor.get_mechanical_angle(),
+        );
+        Self::align_delay(20, abort).await?;
+
+        self.sensor_direction = sensor_direction;
+        self.zero_electric_angle = zero_electric_angle;
+        Ok(AlignResult {
+            sensor_direction,
+            estimated_pole_pairs,
+            zero_electric_angle,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        drivers::mock::MockDriver, fast_math::defines::_1_SQRT3, sensors::base::SensorState,
+        sensors::mock::MockSensor,
+    };
+    use embassy_futures::block_on;
+
+    fn motor(control_type: ControlType) -> Motor<MockDriver, MockSensor> {
+        let mut m = Motor::new(
+            7,
+            1,
+            MockDriver::new(12.0, 6.0),
+            MockSensor::default(),
+            control_type,
+        );
+        m.enable();
+        m
+    }
+
+    /// 去掉中点后的相电压幅值
+    fn amplitude(u: [f32; 3]) -> f32 {
+        let mid = (u[0] + u[1] + u[2]) / 3.0;
+        let sq: f32 = u.iter().map(|x| (x - mid) * (x - mid)).sum();
+        (sq * 2.0 / 3.0).sqrt()
+    }
+
+    #[test]
+    fn torque_output_is_centered() {
+        let mut m = motor(ControlType::Torque);
+        block_on(m.step(1.0));
+        let u = m.driver.phase_voltage;
+        let umax = u[0].max(u[1]).max(u[2]);
+        let umin = u[0].min(u[1]).min(u[2]);
+        assert!(((umax + umin) / 2.0 - 3.0).abs() < 1e-4);
+        assert!((amplitude(u) - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn torque_is_limited_by_driver() {
+        let mut m = motor(ControlType::Torque);
+        block_on(m.step(100.0));
+        let u = m.driver.phase_voltage;
+        assert!(u.iter().all(|x| (0.0..=6.0).contains(x)));
+    }
+
+    #[test]
+    fn modulation_is_selectable() {
+        let mut m = motor(ControlType::Torque);
+        m.modulation = Modulation::Trapezoid120;
+        block_on(m.step(1.0));
+        let u = m.driver.phase_voltage;
+        assert_eq!(u.iter().filter(|x| (**x - 3.0).abs() < 1e-4).count(), 1);
+
+        m.modulation = Modulation::SinePWM;
+        block_on(m.step(1.0));
+        let u = m.driver.phase_voltage;
+        assert!(((u[0] + u[1] + u[2]) / 3.0 - 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn output_follows_bus_voltage() {
+        let mut m = motor(ControlType::Torque);
+        m.set_bus_voltage(4.0);
+        block_on(m.step(100.0));
+        let u = m.driver.phase_voltage;
+        assert!(u.iter().all(|x| (0.0..=4.0).contains(x)));
+        // 限制在SVPWM线性区内, 输出不被截断
+        assert!((amplitude(u) - 4.0 * _1_SQRT3).abs() < 0.05);
+
+        // 过调制只在六边形顶点方向提高幅值
+        m.overmodulation = true;
+        let mut peak = 0.0f32;
+        for i in 0..64 {
+            m.sensor.angle = i as f32 * 0.1;
+            block_on(m.step(100.0));
+            peak = peak.max(amplitude(m.driver.phase_voltage));
+        }
+        assert!(peak > 4.0 * _1_SQRT3 + 0.1);
+    }
+
+    #[test]
+    fn derating_scales_output() {
+        let mut m = motor(ControlType::Torque);
+        block_on(m.step(100.0));
+        let full = amplitude(m.driver.phase_voltage);
+        m.set_derating(0.5);
+        block_on(m.step(100.0));
+        assert!((amplitude(m.driver.phase_voltage) - full * 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn fixed_loop_period_open_loop() {
+        let mut m = motor(ControlType::VelocityOpenLoop);
+        m.set_loop_period(50e-6);
+        for _ in 0..100 {
+            block_on(m.step(10.0));
+        }
+        assert!((m.shaft_angle() - 10.0 * 50e-6 * 100.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn velocity_loop_is_decimated() {
+        let mut m = motor(ControlType::Velocity);
+        m.pid_velocity.p = 1.0;
+        m.pid_velocity.i = 0.0;
+        m.pid_velocity.output_ramp = 0.0;
+        m.set_velocity_decimation(4);
+        block_on(m.step(1.0));
+        let first = amplitude(m.driver.phase_voltage);
+        // 分频周期内目标变化不影响输出
+        for _ in 0..3 {
+            block_on(m.step(3.0));
+            assert!((amplitude(m.driver.phase_voltage) - first).abs() < 0.05);
+        }
+        block_on(m.step(3.0));
+        assert!(amplitude(m.driver.phase_voltage) > first + 1.0);
+    }
+
+    #[test]
+    fn sensor_read_is_decimated_and_extrapolated() {
+        let mut m = motor(ControlType::Torque);
+        block_on(m.init()).unwrap();
+        m.set_loop_period(1e-3);
+        m.set_velocity_decimation(4);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        m.sensor.angle = 0.1;
+        let reads = m.sensor.reads;
+        block_on(m.step(1.0));
+        assert_eq!(m.sensor.reads, reads + 1);
+        let velocity = m.sensor.get_velocity();
+        assert!(velocity > 0.0);
+        let angle = m.sensor_electrical_angle();
+        // 分频周期内不读取传感器, 电角度按速度推算
+        for _ in 0..3 {
+            block_on(m.step(1.0));
+        }
+        assert_eq!(m.sensor.reads, reads + 1);
+        let expected = m.normalize_angle(angle + 7.0 * velocity * 3e-3);
+        assert!((m.sensor_electrical_angle() - expected).abs() < 1e-4);
+        block_on(m.step(1.0));
+        assert_eq!(m.sensor.reads, reads + 2);
+    }
+
+    #[test]
+    fn failed_sensor_read_keeps_extrapolating() {
+        let mut m = motor(ControlType::Torque);
+        block_on(m.init()).unwrap();
+        m.set_loop_period(1e-3);
+        m.set_velocity_decimation(2);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        m.sensor.angle = 0.1;
+        block_on(m.step(1.0));
+        let velocity = m.sensor.get_velocity();
+        let angle = m.sensor_electrical_angle();
+        // 总线被占用时读取失败, 不等待, 继续按速度推算电角度
+        m.sensor.error = Some(SensorError::Spi);
+        for _ in 0..4 {
+            block_on(m.step(1.0));
+        }
+        let expected = m.normalize_angle(angle + 7.0 * velocity * 4e-3);
+        assert!((m.sensor_electrical_angle() - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn profiling_records_step_and_phase_voltage() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static CYCLES: AtomicU32 = AtomicU32::new(0);
+        fn counter() -> u32 {
+            CYCLES.fetch_add(10, Ordering::Relaxed)
+        }
+        let mut m = motor(ControlType::Torque);
+        block_on(m.step(1.0));
+        assert_eq!(m.profile, Profile::new());
+
+        m.set_cycle_counter(counter);
+        block_on(m.step(1.0));
+        block_on(m.step(1.0));
+        assert_eq!(m.profile.step.count, 2);
+        assert_eq!(m.profile.set_phase_voltage.count, 2);
+        // step包含set_phase_voltage的两次读取
+        assert_eq!(m.profile.step.max, 30);
+        assert_eq!(m.profile.set_phase_voltage.max, 10);
+    }
+
+    #[test]
+    fn new_motor_starts_disabled() {
+        let m = Motor::new(
+            7,
+            1,
+            MockDriver::new(12.0, 6.0),
+            MockSensor::default(),
+            ControlType::Torque,
+        );
+        assert!(!m.enabled());
+        assert!(!m.driver.enabled);
+    }
+
+    #[test]
+    fn disabled_motor_does_not_output() {
+        let mut m = motor(ControlType::Torque);
+        m.disable();
+        assert!(!m.driver.enabled);
+        assert!(!m.enabled());
+        let updates = m.driver.updates;
+        block_on(m.step(1.0));
+        assert_eq!(m.driver.updates, updates);
+
+        m.enable();
+        assert!(m.driver.enabled);
+        block_on(m.step(1.0));
+        assert_eq!(m.driver.updates, updates + 1);
+    }
+
+    #[test]
+    fn sensor_electrical_angle_uses_alignment() {
+        let mut m = motor(ControlType::Torque);
+        m.sensor.angle = 0.1;
+        block_on(m.init()).unwrap();
+        m.set_sensor_alignment(1, 0.2);
+        assert!((m.sensor_electrical_angle() - 0.5).abs() < 1e-5);
+        m.set_sensor_alignment(-1, 0.0);
+        assert!((m.sensor_electrical_angle() - (_2PI - 0.7)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sensor_error_keeps_last_angle() {
+        let mut m = motor(ControlType::Velocity);
+        m.sensor.angle = 1.0;
+        block_on(m.init()).unwrap();
+        block_on(m.step(0.0));
+        m.sensor.error = Some(SensorError::Spi);
+        m.sensor.angle = 2.0;
+        block_on(m.step(0.0));
+        assert!((m.shaft_angle() - 1.0).abs() < 1e-6);
+    }
+
+    /// init和正向扫描(含扫描后的一次读取)返回0.5, 之后返回end, 即反向扫描时角度增大
+    struct SweepSensor {
+        end: f32,
+        reads: u32,
+        state: SensorState,
+    }
+
+    fn sweep_motor(end: f32) -> Motor<MockDriver, SweepSensor> {
+        let sensor = SweepSensor {
+            end,
+            reads: 0,
+            state: SensorState::new(),
+        };
+        let mut m = Motor::new(
+            7,
+            1,
+            MockDriver::new(12.0, 6.0),
+            sensor,
+            ControlType::Torque,
+        );
+        block_on(m.init()).unwrap();
+        m
+    }
+
+    impl Sensor for SweepSensor {
+        async fn get_sensor_angle(&mut self) -> Result<f32, SensorError> {
+            self.reads += 1;
+            Ok(if self.reads <= ALIGN_SWEEP_STEPS + 3 {
+                0.5
+            } else {
+                self.end
+            })
+        }
+
+        fn state(&self) -> &SensorState {
+            &self.state
+        }
+
+        fn state_mut(&mut self) -> &mut SensorState {
+            &mut self.state
+        }
+    }
+
+    #[test]
+    fn pole_pair_mismatch_keeps_alignment() {
+        let mut m = sweep_motor(2.0);
+        m.set_sensor_alignment(1, 0.2);
+        // 机械行程1.5rad对应约4对极, 与设置的7对极不符
+        assert!(matches!(
+            block_on(m.align_sensor()),
+            Err(AlignError::PolePairMismatch { estimated: 4 })
+        ));
+        assert_eq!(m.sensor_direction, 1);
+        assert_eq!(m.zero_electric_angle, 0.2);
+    }
+
+    #[test]
+    fn align_ignores_stale_angle_advance() {
+        // 机械行程为一个电周期
+        let end = 0.5 + _2PI / 7.0;
+        let mut m = sweep_motor(end);
+        m.angle_advance = 0.3;
+        let result = block_on(m.align_sensor()).unwrap();
+        assert_eq!(result.sensor_direction, -1);
+        assert_eq!(result.estimated_pole_pairs, 7);
+        assert_eq!(m.angle_advance, 0.0);
+        let expected = m.normalize_angle(-7.0 * end);
+        assert!((result.zero_electric_angle - expected).abs() < 1e-5);
+        assert!(m.sensor_electrical_angle().abs() < 1e-4);
+    }
+
+    #[test]
+    fn abort_stops_alignment() {
+        let mut m = sweep_motor(0.5 + _2PI / 7.0);
+        m.set_sensor_alignment(1, 0.2);
+        let mut calls = 0;
+        let result = block_on(m.align_sensor_with(|| {
+            calls += 1;
+            calls > 10
+        }));
+        assert_eq!(result.err(), Some(AlignError::Aborted));
+        assert_eq!(calls, 11);
+        // 中止后输出零电压, 对齐参数不变
+        let u = m.driver.phase_voltage;
+        assert!(u.iter().all(|x| (x - u[0]).abs() < 1e-6));
+        assert_eq!(m.sensor_direction, 1);
+        assert_eq!(m.zero_electric_angle, 0.2);
+    }
+}
@@ -0,0 +1,340 @@
+//! 过压、欠压、过温、过流(I²t)和栅极驱动故障保护
+
+use crate::{drv8323::faults::FaultStatus, fast_math::transforms::PhaseCurrent, monitor::Readings};
+
+/// I²t使用率超过该值后开始降额
+const I2T_DERATE_START: f32 = 0.8;
+/// I²t使用率低于该值时才允许清除过流跳闸
+const I2T_CLEAR: f32 = 0.5;
+
+/// 跳闸原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TripReason {
+    OverVoltage,
+    UnderVoltage,
+    FetOverTemperature,
+    McuOverTemperature,
+    /// NTC短路或开路, 无法监测功率管温度
+    NtcFault,
+    /// 相电流I²t超限
+    OverCurrent,
+    /// DRV8323锁存的故障位域
+    DrvFault(u32),
+}
+
+/// 保护阈值
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProtectionConfig {
+    pub vbus_max: f32,           // 过压阈值(V)
+    pub vbus_min: f32,           // 欠压阈值(V)
+    pub vbus_hysteresis: f32,    // 电压恢复回差(V)
+    pub fet_temp_derate: f32,    // 功率管开始降额的温度(°C)
+    pub fet_temp_max: f32,       // 功率管过温阈值(°C)
+    pub mcu_temp_max: f32,       // MCU过温阈值(°C)
+    pub temp_hysteresis: f32,    // 温度恢复回差(°C)
+    pub current_continuous: f32, // 允许的连续相电流有效值(A)
+    pub current_peak: f32,       // 短时相电流有效值(A)
+    pub peak_time: f32,          // current_peak允许持续的时间(秒)
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self {
+            vbus_max: 26.0,
+            vbus_min: 9.0,
+            vbus_hysteresis: 1.0,
+            fet_temp_derate: 80.0,
+            fet_temp_max: 100.0,
+            mcu_temp_max: 85.0,
+            temp_hysteresis: 10.0,
+            current_continuous: 10.0,
+            current_peak: 20.0,
+            peak_time: 2.0,
+        }
+    }
+}
+
+impl ProtectionConfig {
+    /// I²t积分的跳闸阈值(A²s)
+    pub fn i2t_limit(&self) -> f32 {
+        (self.current_peak * self.current_peak - self.current_continuous * self.current_continuous)
+            * self.peak_time
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProtectionState {
+    Normal,
+    /// 输出电压按比例降额, 0~1
+    Derated(f32),
+    /// 已跳闸, 输出级必须关闭, 直到调用clear
+    Tripped(TripReason),
+}
+
+/// 每个控制周期评估一次的保护管理器
+///
+/// 跳闸后锁存原因, 测量值回到阈值减去回差以内后才能通过clear清除。
+pub struct ProtectionManager {
+    pub config: ProtectionConfig,
+    i2t: f32,
+    trip: Option<TripReason>,
+}
+
+impl ProtectionManager {
+    pub fn new(config: ProtectionConfig) -> Self {
+        Self {
+            config,
+            i2t: 0.0,
+            trip: None,
+        }
+    }
+
+    /// dt为距上次调用的时间(秒), drv_faults为FaultStatus::bits
+    pub fn update(
+        &mut self,
+        readings: &Readings,
+        current: &PhaseCurrent,
+        drv_faults: u32,
+        dt: f32,
+    ) -> ProtectionState {
+        // 三相平衡时(a²+b²+c²)/3等于相电流有效值的平方
+        let i_sq = (current.a * current.a + current.b * current.b + current.c * current.c) / 3.0;
+        let i_cont = self.config.current_continuous;
+        self.i2t = (self.i2t + (i_sq - i_cont * i_cont) * dt).max(0.0);
+
+        if let Some(reason) = self.trip {
+            return ProtectionState::Tripped(reason);
+        }
+        if let Some(reason) = self.check(readings, drv_faults) {
+            error!("protection trip: {:?}", reason);
+            self.trip = Some(reason);
+            return ProtectionState::Tripped(reason);
+        }
+
+        let c = &self.config;
+        let temp_scale = if readings.fet_temperature > c.fet_temp_derate {
+            (c.fet_temp_max - readings.fet_temperature) / (c.fet_temp_max - c.fet_temp_derate)
+        } else {
+            1.0
+        };
+        let usage = self.i2t_usage();
+        let i2t_scale = if usage > I2T_DERATE_START {
+            (1.0 - usage) / (1.0 - I2T_DERATE_START)
+        } else {
+            1.0
+        };
+        let scale = temp_scale.min(i2t_scale);
+        if scale < 1.0 {
+            ProtectionState::Derated(scale.max(0.0))
+        } else {
+            ProtectionState::Normal
+        }
+    }
+
+    fn check(&self, r: &Readings, drv_faults: u32) -> Option<TripReason> {
+        let c = &self.config;
+        if FaultStatus::from_bits(drv_faults).fault {
+            Some(TripReason::DrvFault(drv_faults))
+        } else if r.vbus > c.vbus_max {
+            Some(TripReason::OverVoltage)
+        } else if r.vbus < c.vbus_min {
+            Some(TripReason::UnderVoltage)
+        } else if r.ntc_fault {
+            Some(TripReason::NtcFault)
+        } else if r.fet_temperature >= c.fet_temp_max {
+            Some(TripReason::FetOverTemperature)
+        } else if r.mcu_temperature >= c.mcu_temp_max {
+            Some(TripReason::McuOverTemperature)
+        } else if self.i2t_usage() >= 1.0 {
+            Some(TripReason::OverCurrent)
+        } else {
+            None
+        }
+    }
+
+    /// 清除跳闸, 仍有条件不满足回差时返回对应的原因
+    pub fn clear(&mut self, r: &Readings, drv_faults: u32) -> Result<(), TripReason> {
+        let c = &self.config;
+        let reason = if FaultStatus::from_bits(drv_faults).fault {
+            Some(TripReason::DrvFault(drv_faults))
+        } else if r.vbus > c.vbus_max - c.vbus_hysteresis {
+            Some(TripReason::OverVoltage)
+        } else if r.vbus < c.vbus_min + c.vbus_hysteresis {
+            Some(TripReason::UnderVoltage)
+        } else if r.ntc_fault {
+            Some(TripReason::NtcFault)
+        } else if r.fet_temperature > c.fet_temp_max - c.temp_hysteresis {
+            Some(TripReason::FetOverTemperature)
+        } else if r.mcu_temperature > c.mcu_temp_max - c.temp_hysteresis {
+            Some(TripReason::McuOverTemperature)
+        } else if self.i2t_usage() > I2T_CLEAR {
+            Some(TripReason::OverCurrent)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(reason),
+            None => {
+                if let Some(reason) = self.trip.take() {
+                    info!("protection cleared: {:?}", reason);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// 最近一次跳闸的原因, 清除后为None
+    pub fn trip_reason(&self) -> Option<TripReason> {
+        self.trip
+    }
+
+    /// I²t积分占跳闸阈值的比例
+    pub fn i2t_usage(&self) -> f32 {
+        self.i2t / self.config.i2t_limit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readings() -> Readings {
+        Readings {
+            vbus: 24.0,
+            fet_temperature: 40.0,
+            mcu_temperature: 40.0,
+            ntc_fault: false,
+        }
+    }
+
+    /// 有效值为rms的三相电流在0相位的瞬时值
+    fn current(rms: f32) -> PhaseCurrent {
+        let amp = rms * core::f32::consts::SQRT_2;
+        PhaseCurrent {
+            a: amp,
+            b: -amp / 2.0,
+            c: -amp / 2.0,
+        }
+    }
+
+    #[test]
+    fn normal_operation() {
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        let state = p.update(&readings(), &current(5.0), 0, 1e-3);
+        assert_eq!(state, ProtectionState::Normal);
+        assert_eq!(p.i2t_usage(), 0.0);
+    }
+
+    #[test]
+    fn over_voltage_trips_and_clears_with_hysteresis() {
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        let mut r = readings();
+        r.vbus = 26.5;
+        let state = p.update(&r, &current(0.0), 0, 1e-3);
+        assert_eq!(state, ProtectionState::Tripped(TripReason::OverVoltage));
+        // 回到阈值以下但仍在回差内, 保持跳闸
+        r.vbus = 25.5;
+        assert_eq!(p.clear(&r, 0), Err(TripReason::OverVoltage));
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Tripped(TripReason::OverVoltage)
+        );
+        r.vbus = 24.0;
+        assert_eq!(p.clear(&r, 0), Ok(()));
+        assert_eq!(p.trip_reason(), None);
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Normal
+        );
+    }
+
+    #[test]
+    fn under_voltage_and_ntc_fault_trip() {
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        let mut r = readings();
+        r.vbus = 8.0;
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Tripped(TripReason::UnderVoltage)
+        );
+
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        let mut r = readings();
+        r.ntc_fault = true;
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Tripped(TripReason::NtcFault)
+        );
+    }
+
+    #[test]
+    fn fet_temperature_derates_then_trips() {
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        let mut r = readings();
+        r.fet_temperature = 90.0;
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Derated(0.5)
+        );
+        r.fet_temperature = 100.0;
+        assert_eq!(
+            p.update(&r, &current(0.0), 0, 1e-3),
+            ProtectionState::Tripped(TripReason::FetOverTemperature)
+        );
+        r.fet_temperature = 95.0;
+        assert_eq!(p.clear(&r, 0), Err(TripReason::FetOverTemperature));
+    }
+
+    #[test]
+    fn i2t_allows_peak_for_peak_time() {
+        let cfg = ProtectionConfig::default();
+        let mut p = ProtectionManager::new(cfg);
+        let r = readings();
+        let mut t = 0.0;
+        let mut derated = false;
+        loop {
+            match p.update(&r, &current(cfg.current_peak), 0, 1e-3) {
+                ProtectionState::Tripped(reason) => {
+                    assert_eq!(reason, TripReason::OverCurrent);
+                    break;
+                }
+                ProtectionState::Derated(_) => derated = true,
+                ProtectionState::Normal => {}
+            }
+            t += 1e-3;
+        }
+        assert!(derated);
+        assert!((t - cfg.peak_time).abs() < 0.01, "{}", t);
+
+        // 电流为0时积分每秒减小current_continuous², 3秒后降到一半以下
+        assert_eq!(p.clear(&r, 0), Err(TripReason::OverCurrent));
+        for _ in 0..3100 {
+            p.update(&r, &current(0.0), 0, 1e-3);
+        }
+        assert_eq!(p.clear(&r, 0), Ok(()));
+    }
+
+    #[test]
+    fn drv_fault_trips_but_warnings_do_not() {
+        let mut p = ProtectionManager::new(ProtectionConfig::default());
+        // FSR2的OTW只是警告
+        let otw = FaultStatus::decode(0, 1 << 7).bits();
+        assert_eq!(
+            p.update(&readings(), &current(0.0), otw, 1e-3),
+            ProtectionState::Normal
+        );
+        let fault = FaultStatus::decode((1 << 10) | (1 << 5), 0).bits();
+        assert_eq!(
+            p.update(&readings(), &current(0.0), fault, 1e-3),
+            ProtectionState::Tripped(TripReason::DrvFault(fault))
+        );
+        assert_eq!(
+            p.clear(&readings(), fault),
+            Err(TripReason::DrvFault(fault))
+        );
+        assert_eq!(p.clear(&readings(), 0), Ok(()));
+    }
+}
@@ -0,0 +1,192 @@
+use embedded_hal_async::spi::{self, Operation};
+
+use super::base::{Sensor, SensorError, SensorState};
+use crate::fast_math::defines::_2PI;
+
+pub const NOP: u16 = 0x0000;
+pub const ERRFL: u16 = 0x0001;
+pub const DIAAGC: u16 = 0x3FFC;
+pub const ANGLEUNC: u16 = 0x3FFE;
+pub const ANGLECOM: u16 = 0x3FFF;
+
+const CMD_READ: u16 = 0x1 << 14;
+const PARITY_BIT: u16 = 0x1 << 15;
+const ERROR_FLAG: u16 = 0x1 << 14;
+const DATA_MASK: u16 = 0x3FFF;
+const CPR: f32 = 16384.0;
+
+/// AS5047P 14位磁编码器
+///
+/// 每帧16位, bit15为偶校验位, 读命令的响应在下一帧返回,
+/// 因此每次读寄存器都发送一帧读命令再发送一帧NOP取回数据。
+pub struct AS5047P<SPI> {
+    spi: SPI,
+    pub error_flags: u16, // 最近一次检测到的ERRFL值
+    state: SensorState,
+}
+
+impl<SPI> AS5047P<SPI>
+where
+    SPI: spi::SpiDevice<u16>,
+{
+    pub fn new(spi: SPI) -> AS5047P<SPI> {
+        AS5047P {
+            spi,
+            error_flags: 0,
+            state: SensorState::new(),
+        }
+    }
+
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    fn with_parity(frame: u16) -> u16 {
+        if (frame & !PARITY_BIT).count_ones() % 2 == 1 {
+            frame | PARITY_BIT
+        } else {
+            frame
+        }
+    }
+
+    async fn transfer(&mut self, frame: u16) -> Result<u16, SensorError> {
+        let mut rx_data = [0u16; 1];
+        self.spi
+            .transaction(&mut [Operation::Transfer(&mut rx_data, &[frame])])
+            .await
+            .map_err(|_| SensorError::Spi)?;
+        Ok(rx_data[0])
+    }
+
+    async fn read_frame(&mut self, reg: u16) -> Result<u16, SensorError> {
+        self.transfer(Self::with_parity(CMD_READ | reg)).await?;
+        let rx = self.transfer(Self::with_parity(CMD_READ | NOP)).await?;
+        if rx.count_ones() % 2 != 0 {
+            return Err(SensorError::Parity);
+        }
+        Ok(rx)
+    }
+
+    pub async fn read_register(&mut self, reg: u16) -> Result<u16, SensorError> {
+        let rx = self.read_frame(reg).await?;
+        if rx & ERROR_FLAG != 0 {
+            // 读ERRFL同时清除错误标志
+            self.error_flags = self.read_frame(ERRFL).await? & DATA_MASK;
+            return Err(SensorError::ErrorFlag);
+        }
+        Ok(rx & DATA_MASK)
+    }
+
+    /// 读取经过动态角度误差补偿的14位原始角度
+    pub async fn read_raw_angle(&mut self) -> Result<u16, SensorError> {
+        self.read_register(ANGLECOM).await
+    }
+}
+
+impl<SPI> Sensor for AS5047P<SPI>
+where
+    SPI: spi::SpiDevice<u16>,
+{
+    async fn get_sensor_angle(&mut self) -> Result<f32, SensorError> {
+        let raw = self.read_raw_angle().await?;
+        Ok(raw as f32 / CPR * _2PI)
+    }
+
+    fn state(&self) -> &SensorState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut SensorState {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::mock::{MockAs5047p, INVCOMM, PARERR};
+    use embassy_futures::block_on;
+    use embedded_hal_async::spi::SpiDevice;
+
+    fn encoder(angle: u16) -> AS5047P<MockAs5047p> {
+        AS5047P::new(MockAs5047p::new(angle))
+    }
+
+    #[test]
+    fn frames_have_even_parity() {
+        assert_eq!(
+            AS5047P::<MockAs5047p>::with_parity(CMD_READ | ANGLECOM),
+            0xFFFF
+        );
+        assert_eq!(AS5047P::<MockAs5047p>::with_parity(CMD_READ | NOP), 0xC000);
+        assert_eq!(
+            AS5047P::<MockAs5047p>::with_parity(CMD_READ | ERRFL),
+            0x4001
+        );
+        let mut enc = encoder(0x1234);
+        block_on(enc.read_raw_angle()).unwrap();
+        assert!(enc.spi.frames.iter().all(|f| f.count_ones() % 2 == 0));
+    }
+
+    #[test]
+    fn read_uses_command_then_nop_frame() {
+        let mut enc = encoder(0x1234);
+        assert_eq!(block_on(enc.read_raw_angle()), Ok(0x1234));
+        assert_eq!(enc.spi.frames, [0xFFFF, 0xC000]);
+        // 第一帧返回的是上一条命令的结果, 不会混入下一次读取
+        enc.spi.angle = 0x0ABC;
+        assert_eq!(block_on(enc.read_raw_angle()), Ok(0x0ABC));
+    }
+
+    #[test]
+    fn angle_is_scaled_to_radians() {
+        let mut enc = encoder(0x2000);
+        let angle = block_on(enc.get_sensor_angle()).unwrap();
+        assert!((angle - _2PI / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn bad_response_parity_is_rejected() {
+        let mut enc = encoder(0x1234);
+        enc.spi.corrupt_parity = true;
+        assert_eq!(block_on(enc.read_raw_angle()), Err(SensorError::Parity));
+    }
+
+    #[test]
+    fn error_flag_reads_and_clears_errfl() {
+        let mut enc = encoder(0x1234);
+        enc.spi.errfl = PARERR;
+        assert_eq!(block_on(enc.read_raw_angle()), Err(SensorError::ErrorFlag));
+        assert_eq!(enc.error_flags, PARERR);
+        assert_eq!(enc.spi.errfl, 0);
+        assert_eq!(&enc.spi.frames[2..], [0x4001, 0xC000]);
+        assert_eq!(block_on(enc.read_raw_angle()), Ok(0x1234));
+    }
+
+    #[test]
+    fn invalid_register_sets_error_flag() {
+        let mut enc = encoder(0x1234);
+        assert_eq!(
+            block_on(enc.read_register(0x0100)),
+            Err(SensorError::ErrorFlag)
+        );
+        assert_eq!(enc.error_flags, INVCOMM);
+    }
+
+    #[test]
+    fn mock_flags_bad_command_parity() {
+        let mut spi = MockAs5047p::default();
+        let mut rx = [0u16; 2];
+        // 0x7FFF校验位应为1
+        block_on(spi.transaction(&mut [Operation::Transfer(&mut rx, &[0x7FFF, 0xC000])])).unwrap();
+        assert_eq!(rx[1] & ERROR_FLAG, ERROR_FLAG);
+        assert_eq!(spi.errfl, PARERR);
+    }
+
+    #[test]
+    fn spi_error_is_reported() {
+        let mut enc = encoder(0x1234);
+        enc.spi.fail = true;
+        assert_eq!(block_on(enc.read_raw_angle()), Err(SensorError::Spi));
+    }
+}
@@ -0,0 +1,159 @@
+use embassy_time::Instant;
+
+use crate::fast_math::defines::_2PI;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SensorError {
+    Spi,
+    Parity,
+    ErrorFlag,
+}
+
+/// 传感器多圈计数和测速状态, 由实现Sensor的驱动持有
+pub struct SensorState {
+    angle_prev: f32, // 上次update读到的单圈机械角度
+    angle_prev_ts: u64,
+    full_rotations: i32,
+    vel_angle_prev: f32, // 上次计算速度时的单圈机械角度
+    vel_angle_prev_ts: u64,
+    vel_full_rotations: i32,
+    velocity: f32,
+    initialized: bool,         // init失败时为false, 由下一次成功的update设置起点
+    pub min_elapsed_time: f32, // 计算速度的最小时间间隔(秒)
+}
+
+impl SensorState {
+    pub fn new() -> Self {
+        Self {
+            angle_prev: 0.0,
+            angle_prev_ts: 0,
+            full_rotations: 0,
+            vel_angle_prev: 0.0,
+            vel_angle_prev_ts: 0,
+            vel_full_rotations: 0,
+            velocity: 0.0,
+            initialized: false,
+            min_elapsed_time: 0.0001,
+        }
+    }
+
+    /// 以当前角度为多圈计数和测速的起点
+    fn seed(&mut self, angle: f32, now_us: u64) {
+        self.angle_prev = angle;
+        self.angle_prev_ts = now_us;
+        self.full_rotations = 0;
+        self.vel_angle_prev = angle;
+        self.vel_angle_prev_ts = now_us;
+        self.vel_full_rotations = 0;
+        self.velocity = 0.0;
+        self.initialized = true;
+    }
+}
+
+impl Default for SensorState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait Sensor {
+    /// 读取单圈机械角度, 范围[0, 2PI)
+    async fn get_sensor_angle(&mut self) -> Result<f32, SensorError>;
+
+    fn state(&self) -> &SensorState;
+
+    fn state_mut(&mut self) -> &mut SensorState;
+
+    /// 初始化多圈计数和测速的起点
+    async fn init(&mut self) -> Result<(), SensorError> {
+        let angle = self.get_sensor_angle().await?;
+        self.state_mut().seed(angle, Instant::now().as_micros());
+        Ok(())
+    }
+
+    /// 读取传感器, 更新多圈计数; 距离上次测速超过min_elapsed_time时更新速度
+    ///
+    /// 未成功init时, 第一次成功读取只作为起点, 避免从0角度误判整圈或产生速度尖峰。
+    async fn update(&mut self) -> Result<(), SensorError> {
+        let angle = self.get_sensor_angle().await?;
+        let now_us = Instant::now().as_micros();
+        let state = self.state_mut();
+        if !state.initialized {
+            state.seed(angle, now_us);
+            return Ok(());
+        }
+
+        // 过零时角度跳变接近2PI, 以此判断整圈
+        let d_angle = angle - state.angle_prev;
+        if d_angle.abs() > 0.8 * _2PI {
+            state.full_rotations += if d_angle > 0.0 { -1 } else { 1 };
+        }
+        state.angle_prev = angle;
+        state.angle_prev_ts = now_us;
+
+        let ts = (now_us - state.vel_angle_prev_ts) as f32 * 1e-6;
+        if ts >= state.min_elapsed_time {
+            state.velocity = ((state.full_rotations - state.vel_full_rotations) as f32 * _2PI
+                + (angle - state.vel_angle_prev))
+                / ts;
+            state.vel_angle_prev = angle;
+            state.vel_angle_prev_ts = now_us;
+            state.vel_full_rotations = state.full_rotations;
+        }
+        Ok(())
+    }
+
+    /// 单圈机械角度[0, 2PI)
+    fn get_mechanical_angle(&self) -> f32 {
+        self.state().angle_prev
+    }
+
+    /// 多圈累计角度, 圈数较多时精度下降, 需要精确值请用get_precise_angle
+    fn get_angle(&self) -> f32 {
+        let state = self.state();
+        state.full_rotations as f32 * _2PI + state.angle_prev
+    }
+
+    fn get_precise_angle(&self) -> f64 {
+        let state = self.state();
+        state.full_rotations as f64 * _2PI as f64 + state.angle_prev as f64
+    }
+
+    fn get_full_rotations(&self) -> i32 {
+        self.state().full_rotations
+    }
+
+    /// 角速度(rad/s)
+    fn get_velocity(&self) -> f32 {
+        self.state().velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::mock::MockSensor;
+    use embassy_futures::block_on;
+
+    #[test]
+    fn first_update_after_failed_init_seeds_state() {
+        let mut sensor = MockSensor::default();
+        sensor.error = Some(SensorError::Spi);
+        assert_eq!(block_on(sensor.init()), Err(SensorError::Spi));
+
+        // 与初始值0相差超过0.8*2PI, 未初始化时会被误判为一整圈
+        sensor.error = None;
+        sensor.angle = 6.0;
+        block_on(sensor.update()).unwrap();
+        assert_eq!(sensor.get_full_rotations(), 0);
+        assert_eq!(sensor.get_angle(), 6.0);
+        assert_eq!(sensor.get_velocity(), 0.0);
+
+        // 之后正常过零计圈
+        sensor.angle = 0.1;
+        block_on(sensor.update()).unwrap();
+        assert_eq!(sensor.get_full_rotations(), 1);
+    }
+}
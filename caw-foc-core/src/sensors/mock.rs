@@ -0,0 +1,159 @@
+//! 主机测试用的位置传感器和AS5047P寄存器模型
+
+use embedded_hal_async::spi::{self, ErrorKind, Operation};
+
+use super::{
+    as5047p::{ANGLECOM, ANGLEUNC, DIAAGC, ERRFL, NOP},
+    base::{Sensor, SensorError, SensorState},
+};
+
+#[derive(Default)]
+pub struct MockSensor {
+    /// 下次读取返回的单圈机械角度
+    pub angle: f32,
+    pub error: Option<SensorError>,
+    /// get_sensor_angle的调用次数
+    pub reads: u32,
+    state: SensorState,
+}
+
+impl Sensor for MockSensor {
+    async fn get_sensor_angle(&mut self) -> Result<f32, SensorError> {
+        self.reads += 1;
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.angle),
+        }
+    }
+
+    fn state(&self) -> &SensorState {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut SensorState {
+        &mut self.state
+    }
+}
+
+/// ERRFL的PARERR位, 命令帧校验错误
+pub const PARERR: u16 = 0x1 << 2;
+/// ERRFL的INVCOMM位, 读取不存在的寄存器
+pub const INVCOMM: u16 = 0x1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockSpiError;
+
+impl spi::Error for MockSpiError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// AS5047P的SPI模型, 实现`SpiDevice<u16>`
+///
+/// 命令帧: bit15为偶校验位, bit14为读写位(1为读), bit13-0为地址;
+/// 每帧返回上一帧命令的结果, bit14为EF, 上一帧命令出错或ERRFL非0时置位。
+/// 读ERRFL后清除错误标志。
+#[derive(Default)]
+pub struct MockAs5047p {
+    /// ANGLECOM的值
+    pub angle: u16,
+    pub errfl: u16,
+    /// 发送的命令帧记录
+    pub frames: Vec<u16>,
+    /// 为true时返回帧的校验位取反
+    pub corrupt_parity: bool,
+    /// 为true时事务返回错误
+    pub fail: bool,
+    response: u16,
+}
+
+impl MockAs5047p {
+    pub fn new(angle: u16) -> Self {
+        Self {
+            angle,
+            ..Default::default()
+        }
+    }
+
+    fn with_parity(frame: u16) -> u16 {
+        let frame = frame & 0x7FFF;
+        if frame.count_ones() % 2 == 1 {
+            frame | (0x1 << 15)
+        } else {
+            frame
+        }
+    }
+
+    fn frame(&mut self, tx: u16) -> u16 {
+        self.frames.push(tx);
+        let rx = self.response;
+        let addr = tx & 0x3FFF;
+        let data = if tx.count_ones() % 2 == 1 {
+            self.errfl |= PARERR;
+            0
+        } else if tx & (0x1 << 14) == 0 {
+            // 驱动只读寄存器, 写命令只返回原值
+            0
+        } else {
+            match addr {
+                NOP => 0,
+                ERRFL => core::mem::take(&mut self.errfl),
+                DIAAGC => 0x0100,
+                ANGLEUNC | ANGLECOM => self.angle & 0x3FFF,
+                _ => {
+                    self.errfl |= INVCOMM;
+                    0
+                }
+            }
+        };
+        let ef = if self.errfl != 0 { 0x1 << 14 } else { 0 };
+        self.response = Self::with_parity(ef | data);
+        if self.corrupt_parity {
+            rx ^ (0x1 << 15)
+        } else {
+            rx
+        }
+    }
+}
+
+impl spi::ErrorType for MockAs5047p {
+    type Error = MockSpiError;
+}
+
+impl spi::SpiDevice<u16> for MockAs5047p {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u16>],
+    ) -> Result<(), MockSpiError> {
+        if self.fail {
+            return Err(MockSpiError);
+        }
+        for op in operations {
+            match op {
+                Operation::Transfer(rx, tx) => {
+                    for (r, t) in rx.iter_mut().zip(tx.iter()) {
+                        *r = self.frame(*t);
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    for w in buf.iter_mut() {
+                        *w = self.frame(*w);
+                    }
+                }
+                Operation::Write(tx) => {
+                    for t in tx.iter() {
+                        self.frame(*t);
+                    }
+                }
+                Operation::Read(rx) => {
+                    for r in rx.iter_mut() {
+                        *r = self.frame(0);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
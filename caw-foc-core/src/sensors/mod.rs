@@ -0,0 +1,4 @@
+pub mod as5047p;
+pub mod base;
+#[cfg(test)]
+pub mod mock;
@@ -0,0 +1,179 @@
+//! 控制循环的周期、执行时间和CPU负载统计
+
+/// 读取自由运行的32位周期计数器, 例如DWT CYCCNT
+pub type CycleCounter = fn() -> u32;
+
+/// 最小/最大/平均值统计, 单位由调用者决定(例如CPU周期)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimingStats {
+    pub min: u32,
+    pub max: u32,
+    pub count: u32,
+    sum: u64,
+}
+
+impl Default for TimingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimingStats {
+    pub const fn new() -> Self {
+        Self {
+            min: u32::MAX,
+            max: 0,
+            count: 0,
+            sum: 0,
+        }
+    }
+
+    pub fn record(&mut self, value: u32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as u64;
+        self.count += 1;
+    }
+
+    /// 没有样本时返回0
+    pub fn average(&self) -> u32 {
+        if self.count == 0 {
+            0
+        } else {
+            (self.sum / self.count as u64) as u32
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// 控制循环的实测时序, 单位为CPU周期
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LoopTiming {
+    pub period: TimingStats,    // 相邻两次循环开始的间隔
+    pub execution: TimingStats, // 单次循环的执行时间
+    pub overruns: u32,          // 执行时间超过周期, 丢失触发的次数
+}
+
+impl LoopTiming {
+    pub const fn new() -> Self {
+        Self {
+            period: TimingStats::new(),
+            execution: TimingStats::new(),
+            overruns: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Motor各段代码的执行时间, 单位为CPU周期
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Profile {
+    pub step: TimingStats,              // Motor::step, 包括读取传感器
+    pub set_phase_voltage: TimingStats, // 电压限幅、调制和设置占空比
+}
+
+impl Profile {
+    pub const fn new() -> Self {
+        Self {
+            step: TimingStats::new(),
+            set_phase_voltage: TimingStats::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// 上报用的性能统计快照
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProfileReport {
+    pub cpu_load: f32, // 0~1
+    pub loop_timing: LoopTiming,
+    pub motor: Profile,
+    pub sincos: TimingStats, // fast_sincos单次调用
+}
+
+impl ProfileReport {
+    pub const fn new() -> Self {
+        Self {
+            cpu_load: 0.0,
+            loop_timing: LoopTiming::new(),
+            motor: Profile::new(),
+            sincos: TimingStats::new(),
+        }
+    }
+}
+
+/// 执行f并把消耗的周期数记录到stats
+pub fn measure<R>(counter: CycleCounter, stats: &mut TimingStats, f: impl FnOnce() -> R) -> R {
+    let start = counter();
+    let result = f();
+    stats.record(counter().wrapping_sub(start));
+    result
+}
+
+/// 统计窗口内的CPU负载(0~1), idle和total为同一计数器的周期数
+pub fn cpu_load(idle: u32, total: u32) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        1.0 - (idle as f32 / total as f32).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn min_max_average() {
+        let mut s = TimingStats::new();
+        assert_eq!(s.average(), 0);
+        for v in [10, 30, 20] {
+            s.record(v);
+        }
+        assert_eq!((s.min, s.max, s.average(), s.count), (10, 30, 20, 3));
+        s.reset();
+        assert_eq!(s, TimingStats::new());
+    }
+
+    #[test]
+    fn average_does_not_overflow() {
+        let mut s = TimingStats::new();
+        for _ in 0..4 {
+            s.record(u32::MAX);
+        }
+        assert_eq!(s.average(), u32::MAX);
+    }
+
+    #[test]
+    fn measure_records_elapsed_cycles() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        // 每次读取前进100个周期, 并跨越u32回绕
+        static CYCLES: AtomicU32 = AtomicU32::new(u32::MAX - 50);
+        fn counter() -> u32 {
+            CYCLES.fetch_add(100, Ordering::Relaxed)
+        }
+        let mut s = TimingStats::new();
+        assert_eq!(measure(counter, &mut s, || 42), 42);
+        assert_eq!((s.min, s.max, s.count), (100, 100, 1));
+    }
+
+    #[test]
+    fn cpu_load_from_idle_cycles() {
+        assert_eq!(cpu_load(750, 1000), 0.25);
+        assert_eq!(cpu_load(1200, 1000), 0.0);
+        assert_eq!(cpu_load(0, 0), 0.0);
+    }
+}
@@ -1,7 +1,8 @@
 MEMORY
 {
-  FLASH : ORIGIN = 0x08000000, LENGTH = 512K
+  /* 最后4K(两页)保留给配置存储, 见src/config/storage.rs */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 508K
   RAM   : ORIGIN = 0x20000000, LENGTH = 128K
 }
 
-_stack_start = ORIGIN(RAM) + LENGTH(RAM);
\ No newline at end of file
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
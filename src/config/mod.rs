@@ -0,0 +1,3 @@
+pub mod storage;
+
+pub use caw_foc_core::config::*;
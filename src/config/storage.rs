@@ -0,0 +1,125 @@
+use defmt::{debug, Format};
+use embassy_stm32::flash::{Blocking, Flash};
+
+use super::{DriveConfig, RECORD_LEN};
+
+// Flash最后两页(各2K)保留给配置, memory.x中FLASH长度相应减少
+const PAGE_SIZE: u32 = 2048;
+const PAGE_OFFSETS: [u32; 2] = [512 * 1024 - 2 * PAGE_SIZE, 512 * 1024 - PAGE_SIZE];
+// 每条记录占用一个槽, 长度为8字节(双字)的整数倍
+const SLOT_SIZE: usize = 256;
+const SLOTS_PER_PAGE: usize = PAGE_SIZE as usize / SLOT_SIZE;
+// 配置记录变长时在编译期发现槽放不下
+const _: () = assert!(RECORD_LEN <= SLOT_SIZE && SLOT_SIZE % 8 == 0);
+
+#[derive(Debug, Format, PartialEq, Clone, Copy)]
+pub enum ConfigError {
+    Flash,
+    Verify,
+}
+
+/// 配置存储
+///
+/// 两页轮流使用, 每次保存写入当前页的下一个空槽, 当前页写满后擦除另一页继续写,
+/// 加载时取所有有效记录中序号最大的一条。写入过程中掉电时旧记录仍然有效。
+pub struct ConfigStorage {
+    flash: Flash<'static, Blocking>,
+    seq: u32,
+    page: usize,
+    slot: usize, // 下一个待写入的槽, 为0时写入前需要擦除该页
+}
+
+impl ConfigStorage {
+    pub fn new(flash: Flash<'static, Blocking>) -> Self {
+        Self {
+            flash,
+            seq: 0,
+            page: 0,
+            slot: 0,
+        }
+    }
+
+    fn slot_offset(page: usize, slot: usize) -> u32 {
+        PAGE_OFFSETS[page] + (slot * SLOT_SIZE) as u32
+    }
+
+    fn read_slot(&mut self, page: usize, slot: usize) -> Result<[u8; SLOT_SIZE], ConfigError> {
+        let mut buf = [0u8; SLOT_SIZE];
+        self.flash
+            .blocking_read(Self::slot_offset(page, slot), &mut buf)
+            .map_err(|_| ConfigError::Flash)?;
+        Ok(buf)
+    }
+
+    /// 加载最新的有效配置
+    pub fn load(&mut self) -> Option<DriveConfig> {
+        let mut latest: Option<(DriveConfig, u32, usize, usize)> = None;
+        for page in 0..PAGE_OFFSETS.len() {
+            for slot in 0..SLOTS_PER_PAGE {
+                let Ok(buf) = self.read_slot(page, slot) else {
+                    continue;
+                };
+                if let Some((cfg, seq)) = DriveConfig::decode(&buf) {
+                    if latest.map_or(true, |(_, s, _, _)| seq > s) {
+                        latest = Some((cfg, seq, page, slot));
+                    }
+                }
+            }
+        }
+
+        let (cfg, seq, page, slot) = latest?;
+        debug!("config loaded: seq={} page={} slot={}", seq, page, slot);
+        self.seq = seq;
+        if slot + 1 < SLOTS_PER_PAGE {
+            self.page = page;
+            self.slot = slot + 1;
+        } else {
+            self.page = 1 - page;
+            self.slot = 0;
+        }
+        Some(cfg)
+    }
+
+    pub fn save(&mut self, cfg: &DriveConfig) -> Result<(), ConfigError> {
+        // 槽不为空(上次写入中断等)时换到另一页
+        if self.slot != 0
+            && self
+                .read_slot(self.page, self.slot)?
+                .iter()
+                .any(|b| *b != 0xFF)
+        {
+            self.page = 1 - self.page;
+            self.slot = 0;
+        }
+        if self.slot == 0 {
+            let from = PAGE_OFFSETS[self.page];
+            self.flash
+                .blocking_erase(from, from + PAGE_SIZE)
+                .map_err(|_| ConfigError::Flash)?;
+        }
+
+        let seq = self.seq.wrapping_add(1);
+        let mut buf = [0u8; SLOT_SIZE];
+        buf[..RECORD_LEN].copy_from_slice(&cfg.encode(seq));
+        self.flash
+            .blocking_write(Self::slot_offset(self.page, self.slot), &buf)
+            .map_err(|_| ConfigError::Flash)?;
+
+        let written = self.read_slot(self.page, self.slot)?;
+        if DriveConfig::decode(&written).map(|(_, s)| s) != Some(seq) {
+            return Err(ConfigError::Verify);
+        }
+        debug!(
+            "config saved: seq={} page={} slot={}",
+            seq, self.page, self.slot
+        );
+
+        self.seq = seq;
+        self.slot += 1;
+        if self.slot >= SLOTS_PER_PAGE {
+            self.page = 1 - self.page;
+            self.slot = 0;
+        }
+        Ok(())
+    }
+}
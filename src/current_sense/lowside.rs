@@ -0,0 +1,189 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use defmt::Format;
+use embassy_stm32::{
+    adc::Adc,
+    gpio::Flex,
+    interrupt::{self, InterruptExt, Priority},
+    pac,
+    peripherals::ADC1,
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+
+use crate::{drivers::tim1::PWM_FREQUENCY_HZ, CurrentSenseResources};
+use caw_foc_core::{current_sense::base::CurrentSense, fast_math::transforms::PhaseCurrent};
+
+const ADC_VREF: f32 = 3.3;
+const ADC_RESOLUTION: f32 = 4095.0;
+
+// SOA/SOB/SOC对应的ADC1通道
+const SOA_CHANNEL: u32 = 1;
+const SOB_CHANNEL: u32 = 2;
+const SOC_CHANNEL: u32 = 3;
+
+// 零电流偏置允许偏离VREF/2的范围(V)
+const OFFSET_WINDOW: f32 = 0.2;
+
+// ADC_ISR.JEOS
+const JEOS: u32 = 1 << 6;
+
+/// 每CONTROL_DECIMATION次注入转换(PWM周期)触发一次控制循环
+pub const CONTROL_DECIMATION: u32 = 2;
+/// 控制循环频率, 40kHz PWM下为20kHz
+pub const CONTROL_FREQUENCY_HZ: u32 = PWM_FREQUENCY_HZ / CONTROL_DECIMATION;
+
+/// 新的电流采样可用, 由ADC1_2中断发出
+pub static CURRENT_SAMPLE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+/// 每次注入转换完成都发出, 只用于零点校准, 不与控制循环竞争CURRENT_SAMPLE_SIGNAL
+static CALIBRATION_SAMPLE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+/// 已发出的CURRENT_SAMPLE_SIGNAL次数, 用于检测控制循环丢失的触发
+pub static CONTROL_TICKS: AtomicU32 = AtomicU32::new(0);
+static SAMPLE_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// 注入序列转换完成中断
+#[interrupt]
+fn ADC1_2() {
+    let regs = pac::ADC1;
+    if regs.isr().read().0 & JEOS == 0 {
+        return;
+    }
+    regs.isr().write(|w| w.0 = JEOS);
+    CALIBRATION_SAMPLE_SIGNAL.signal(());
+    if SAMPLE_COUNT.fetch_add(1, Ordering::Relaxed) % CONTROL_DECIMATION == 0 {
+        CONTROL_TICKS.fetch_add(1, Ordering::Relaxed);
+        CURRENT_SAMPLE_SIGNAL.signal(());
+    }
+}
+
+#[derive(Debug, Format, PartialEq, Clone, Copy)]
+pub enum CurrentSenseError {
+    /// 某一相的零电流偏置超出允许范围, phase: 0~2对应A~C
+    OffsetOutOfRange { phase: u8, offset: f32 },
+    /// 采样次数为0, 无法计算偏置
+    NoSamples,
+}
+
+/// DRV8323 SOx输出的下桥臂电流采样
+///
+/// ADC1注入组由TIM1 TRGO触发, 每个PWM周期依次转换SOA、SOB、SOC,
+/// 转换结果保存在JDR1~JDR3中, 读取时总是最近一次PWM周期的采样值。
+/// 注入序列完成中断按CONTROL_DECIMATION分频后发出CURRENT_SAMPLE_SIGNAL, 驱动控制循环。
+pub struct LowsideCurrentSense {
+    _adc: Adc<'static, ADC1>,
+    _pins: [Flex<'static>; 3],
+    shunt_resistor: f32, // 采样电阻(Ω)
+    gain: f32,           // CSA放大倍数(V/V)
+    pub offset_a: f32,   // 零电流时的SOx电压(V)
+    pub offset_b: f32,
+    pub offset_c: f32,
+}
+
+impl LowsideCurrentSense {
+    pub fn new(r: CurrentSenseResources, shunt_resistor: f32, gain: f32) -> Self {
+        let adc = Adc::new(r.adc);
+
+        let mut soa = Flex::new(r.soa);
+        let mut sob = Flex::new(r.sob);
+        let mut soc = Flex::new(r.soc);
+        soa.set_as_analog();
+        sob.set_as_analog();
+        soc.set_as_analog();
+
+        let regs = pac::ADC1;
+        // 采样时间 24.5 cycles
+        regs.smpr(0).modify(|w| {
+            for ch in [SOA_CHANNEL, SOB_CHANNEL, SOC_CHANNEL] {
+                w.0 &= !(0b111 << (ch * 3));
+                w.0 |= 0b011 << (ch * 3);
+            }
+        });
+        // JL = 2(3次转换), JEXTSEL = 0(TIM1_TRGO), JEXTEN = 01(上升沿)
+        regs.jsqr().modify(|w| {
+            w.0 =
+                0b10 | (0b01 << 7) | (SOA_CHANNEL << 9) | (SOB_CHANNEL << 15) | (SOC_CHANNEL << 21);
+        });
+        // JEOSIE, 中断优先级高于控制循环所在的中断执行器
+        regs.ier().modify(|w| w.0 |= JEOS);
+        interrupt::ADC1_2.set_priority(Priority::P5);
+        // SAFETY: ADC1_2中断处理函数只访问ADC1的状态寄存器和原子变量
+        unsafe { interrupt::ADC1_2.enable() };
+        // JADSTART, 之后每次触发自动转换
+        regs.cr().modify(|w| w.0 |= 1 << 3);
+
+        // DRV8323 VREF_DIV使能时零电流输出为VREF/2
+        let offset = ADC_VREF / 2.0;
+
+        Self {
+            _adc: adc,
+            _pins: [soa, sob, soc],
+            shunt_resistor,
+            gain,
+            offset_a: offset,
+            offset_b: offset,
+            offset_c: offset,
+        }
+    }
+
+    /// 测量零电流时各相的偏置电压
+    ///
+    /// 调用时所有相必须处于关断或相同占空比(无电流流过), 每次等待新的注入转换结果,
+    /// 共平均samples次。samples为0或任一相偏置超出VREF/2 ± OFFSET_WINDOW时返回错误且不更新偏置。
+    pub async fn calibrate_offsets(&mut self, samples: u32) -> Result<(), CurrentSenseError> {
+        if samples == 0 {
+            return Err(CurrentSenseError::NoSamples);
+        }
+        let mut sum = [0u32; 3];
+        for _ in 0..samples {
+            self.wait_conversion().await;
+            for (rank, s) in sum.iter_mut().enumerate() {
+                *s += self.read_raw(rank);
+            }
+        }
+
+        let mut offsets = [0.0f32; 3];
+        for (phase, s) in sum.iter().enumerate() {
+            let offset = *s as f32 / samples as f32 * ADC_VREF / ADC_RESOLUTION;
+            // NaN与任何值比较都为false, 需要单独检查
+            if !offset.is_finite() || (offset - ADC_VREF / 2.0).abs() > OFFSET_WINDOW {
+                return Err(CurrentSenseError::OffsetOutOfRange {
+                    phase: phase as u8,
+                    offset,
+                });
+            }
+            offsets[phase] = offset;
+        }
+        self.offset_a = offsets[0];
+        self.offset_b = offsets[1];
+        self.offset_c = offsets[2];
+
+        Ok(())
+    }
+
+    /// 等待下一次注入转换完成, 使用独立的信号, 控制循环运行时也不会取走其触发
+    async fn wait_conversion(&self) {
+        CALIBRATION_SAMPLE_SIGNAL.reset();
+        CALIBRATION_SAMPLE_SIGNAL.wait().await;
+    }
+
+    fn read_raw(&self, rank: usize) -> u32 {
+        pac::ADC1.jdr(rank).read().0 & 0xFFFF
+    }
+
+    fn read_voltage(&self, rank: usize) -> f32 {
+        self.read_raw(rank) as f32 * ADC_VREF / ADC_RESOLUTION
+    }
+
+    fn to_current(&self, voltage: f32, offset: f32) -> f32 {
+        // V_SOx = V_REF/2 - G * (V_SPx - V_SNx)
+        (offset - voltage) / (self.gain * self.shunt_resistor)
+    }
+}
+
+impl CurrentSense for LowsideCurrentSense {
+    fn get_phase_currents(&mut self) -> PhaseCurrent {
+        PhaseCurrent {
+            a: self.to_current(self.read_voltage(0), self.offset_a),
+            b: self.to_current(self.read_voltage(1), self.offset_b),
+            c: self.to_current(self.read_voltage(2), self.offset_c),
+        }
+    }
+}
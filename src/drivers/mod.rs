@@ -6,6 +6,6 @@
  * @FilePath: /caw-foc-rs/src/drivers/mod.rs
  * @Description: 这是默认设置,请设置`customMade`, 打开koroFileHeader查看配置 进行设置: https://github.com/OBKoro1/koro1FileHeader/wiki/%E9%85%8D%E7%BD%AE
  */
-pub mod base;
 pub mod pwmx3;
 pub mod pwmx6;
+pub mod tim1;
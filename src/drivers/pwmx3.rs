@@ -1,9 +1,10 @@
-use super::base::BaseDriver;
+use super::tim1;
+use caw_foc_core::{constrain, drivers::base::BaseDriver};
 
 use embassy_stm32::{
     gpio::{Level, Output, OutputType, Speed},
     peripherals::TIM1,
-    time::khz,
+    time::Hertz,
     timer::{
         low_level::CountingMode,
         simple_pwm::{PwmPin, SimplePwm},
@@ -11,7 +12,7 @@ use embassy_stm32::{
     },
 };
 
-use crate::{constrain, PwmTimResources};
+use crate::PwmTimResources;
 
 pub struct PWMX3 {
     pwm: SimplePwm<'static, TIM1>,
@@ -42,7 +43,7 @@ impl PWMX3 {
             Some(ch2),
             Some(ch3),
             None,
-            khz(40),
+            Hertz(tim1::PWM_FREQUENCY_HZ),
             CountingMode::CenterAlignedBothInterrupts,
         );
         pwm.set_duty(Channel::Ch1, 0);
@@ -53,6 +54,8 @@ impl PWMX3 {
         pwm.enable(Channel::Ch2);
         pwm.enable(Channel::Ch3);
 
+        tim1::enable_adc_trigger();
+
         let max_duty = pwm.get_max_duty() as f32;
 
         Self {
@@ -69,13 +72,16 @@ impl PWMX3 {
 
 impl BaseDriver for PWMX3 {
     fn set_pwm(&mut self, mut ua: f32, mut ub: f32, mut uc: f32) {
-        ua = constrain!(ua, 0.0, self.voltage_limit);
-        ub = constrain!(ub, 0.0, self.voltage_limit);
-        uc = constrain!(uc, 0.0, self.voltage_limit);
+        let limit = self.output_limit();
+        ua = constrain!(ua, 0.0, limit);
+        ub = constrain!(ub, 0.0, limit);
+        uc = constrain!(uc, 0.0, limit);
 
-        let dc_a = constrain!(ua / self.voltage_power_supply, 0.0, 1.0);
-        let dc_b = constrain!(ub / self.voltage_power_supply, 0.0, 1.0);
-        let dc_c = constrain!(uc / self.voltage_power_supply, 0.0, 1.0);
+        // 按实测母线电压计算占空比, 母线电压未测得时limit为0, 输出0占空比
+        let vps = self.voltage_power_supply.max(f32::EPSILON);
+        let dc_a = constrain!(ua / vps, 0.0, 1.0);
+        let dc_b = constrain!(ub / vps, 0.0, 1.0);
+        let dc_c = constrain!(uc / vps, 0.0, 1.0);
 
         // debug!("dc_a:{:?} dc_b:{:?} dc_c:{:?}", dc_a, dc_b, dc_c);
 
@@ -86,4 +92,37 @@ impl BaseDriver for PWMX3 {
         self.pwm
             .set_duty(Channel::Ch3, (dc_c * self.max_duty) as u32);
     }
+
+    fn voltage_limit(&self) -> f32 {
+        self.voltage_limit
+    }
+
+    fn voltage_power_supply(&self) -> f32 {
+        self.voltage_power_supply
+    }
+
+    fn set_voltage_power_supply(&mut self, voltage: f32) {
+        self.voltage_power_supply = voltage;
+    }
+
+    fn enable(&mut self) {
+        self.pwm.enable(Channel::Ch1);
+        self.pwm.enable(Channel::Ch2);
+        self.pwm.enable(Channel::Ch3);
+        // 3xPWM模式下INLx为高时对应半桥工作
+        self.ch1n.set_high();
+        self.ch2n.set_high();
+        self.ch3n.set_high();
+    }
+
+    fn disable(&mut self) {
+        self.set_pwm(0.0, 0.0, 0.0);
+        // INLx为低时半桥为高阻态
+        self.ch1n.set_low();
+        self.ch2n.set_low();
+        self.ch3n.set_low();
+        self.pwm.disable(Channel::Ch1);
+        self.pwm.disable(Channel::Ch2);
+        self.pwm.disable(Channel::Ch3);
+    }
 }
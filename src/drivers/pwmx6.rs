@@ -1,10 +1,11 @@
-use super::base::BaseDriver;
+use super::tim1;
+use caw_foc_core::{constrain, drivers::base::BaseDriver};
 use defmt::debug;
 use embassy_stm32::timer::complementary_pwm::{ComplementaryPwm, ComplementaryPwmPin};
 use embassy_stm32::{
     gpio::{Level, Output, OutputType, Speed},
     peripherals::TIM1,
-    time::khz,
+    time::Hertz,
     timer::{
         low_level::CountingMode,
         simple_pwm::{PwmPin, SimplePwm},
@@ -12,7 +13,7 @@ use embassy_stm32::{
     },
 };
 
-use crate::{constrain, PwmTimResources};
+use crate::PwmTimResources;
 
 pub struct PWMX6 {
     pwm: ComplementaryPwm<'static, TIM1>,
@@ -41,7 +42,7 @@ impl PWMX6 {
             Some(ch3n),
             None,
             None,
-            khz(40),
+            Hertz(tim1::PWM_FREQUENCY_HZ),
             CountingMode::CenterAlignedBothInterrupts,
         );
         pwm.set_duty(Channel::Ch1, 0);
@@ -52,6 +53,8 @@ impl PWMX6 {
         pwm.enable(Channel::Ch2);
         pwm.enable(Channel::Ch3);
 
+        tim1::enable_adc_trigger();
+
         let max_duty = pwm.get_max_duty() as f32;
 
         Self {
@@ -65,13 +68,16 @@ impl PWMX6 {
 
 impl BaseDriver for PWMX6 {
     fn set_pwm(&mut self, mut ua: f32, mut ub: f32, mut uc: f32) {
-        ua = constrain!(ua, 0.0, self.voltage_limit);
-        ub = constrain!(ub, 0.0, self.voltage_limit);
-        uc = constrain!(uc, 0.0, self.voltage_limit);
+        let limit = self.output_limit();
+        ua = constrain!(ua, 0.0, limit);
+        ub = constrain!(ub, 0.0, limit);
+        uc = constrain!(uc, 0.0, limit);
 
-        let dc_a = constrain!(ua / self.voltage_power_supply, 0.0, 1.0);
-        let dc_b = constrain!(ub / self.voltage_power_supply, 0.0, 1.0);
-        let dc_c = constrain!(uc / self.voltage_power_supply, 0.0, 1.0);
+        // 按实测母线电压计算占空比, 母线电压未测得时limit为0, 输出0占空比
+        let vps = self.voltage_power_supply.max(f32::EPSILON);
+        let dc_a = constrain!(ua / vps, 0.0, 1.0);
+        let dc_b = constrain!(ub / vps, 0.0, 1.0);
+        let dc_c = constrain!(uc / vps, 0.0, 1.0);
 
         self.pwm
             .set_duty(Channel::Ch1, (dc_a * self.max_duty) as u16);
@@ -80,4 +86,29 @@ impl BaseDriver for PWMX6 {
         self.pwm
             .set_duty(Channel::Ch3, (dc_c * self.max_duty) as u16);
     }
+
+    fn voltage_limit(&self) -> f32 {
+        self.voltage_limit
+    }
+
+    fn voltage_power_supply(&self) -> f32 {
+        self.voltage_power_supply
+    }
+
+    fn set_voltage_power_supply(&mut self, voltage: f32) {
+        self.voltage_power_supply = voltage;
+    }
+
+    fn enable(&mut self) {
+        self.pwm.enable(Channel::Ch1);
+        self.pwm.enable(Channel::Ch2);
+        self.pwm.enable(Channel::Ch3);
+    }
+
+    fn disable(&mut self) {
+        self.set_pwm(0.0, 0.0, 0.0);
+        self.pwm.disable(Channel::Ch1);
+        self.pwm.disable(Channel::Ch2);
+        self.pwm.disable(Channel::Ch3);
+    }
 }
@@ -0,0 +1,36 @@
+use embassy_stm32::pac;
+
+/// PWM频率, 中心对齐模式下每个周期触发一次ADC注入转换
+pub const PWM_FREQUENCY_HZ: u32 = 40_000;
+
+/// 配置TIM1 TRGO在计数器接近峰值时产生上升沿, 用于触发ADC注入转换
+///
+/// 中心对齐模式下计数器位于峰值时所有上桥臂关闭、下桥臂导通,
+/// 此时采样下桥臂分流电阻的电流最稳定。CH4不输出到引脚, 仅用OC4REF作为触发源。
+pub fn enable_adc_trigger() {
+    let tim = pac::TIM1;
+    let arr = tim.arr().read().0;
+    tim.ccr(3).modify(|w| w.0 = arr - 1);
+    // OC4M = PWM模式2(0b0111), 计数器 >= CCR4 时OC4REF有效
+    tim.ccmr_output(1).modify(|w| {
+        w.0 &= !((0b111 << 12) | (1 << 24));
+        w.0 |= 0b111 << 12;
+    });
+    // MMS = 0b111, OC4REF作为TRGO
+    tim.cr2().modify(|w| {
+        w.0 &= !(0b111 << 4);
+        w.0 |= 0b111 << 4;
+    });
+}
+
+/// 清除BDTR.MOE, 立即关闭TIM1全部PWM输出
+///
+/// 不经过PWM驱动, 故障处理中可以在任意任务里调用。
+pub fn force_outputs_off() {
+    pac::TIM1.bdtr().modify(|w| w.0 &= !(1 << 15));
+}
+
+/// 重新置位BDTR.MOE, 恢复PWM输出
+pub fn enable_outputs() {
+    pac::TIM1.bdtr().modify(|w| w.0 |= 1 << 15);
+}
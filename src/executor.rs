@@ -0,0 +1,57 @@
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use cortex_m::{
+    asm, interrupt,
+    interrupt::InterruptNumber,
+    peripheral::{DWT, NVIC},
+};
+use embassy_executor::{raw, Spawner};
+use embassy_stm32::interrupt::{Interrupt, InterruptExt};
+
+/// 唤醒线程模式执行器的中断, 保持禁止, 只用其挂起位产生事件
+///
+/// embassy-executor的__pender把context当作中断号挂起, 与InterruptExecutor相同,
+/// 不依赖executor-thread的私有约定。FMAC未使用。
+const WAKE_IRQ: Interrupt = Interrupt::FMAC;
+
+/// 线程模式执行器在WFE中等待的累计周期数, 由profiling_task读取并清零
+pub static IDLE_CYCLES: AtomicU32 = AtomicU32::new(0);
+
+/// 统计空闲时间的线程模式执行器
+///
+/// 与embassy_executor::Executor相同, 没有就绪任务时执行WFE, 另外用DWT CYCCNT记录等待的周期数。
+/// 有任务就绪时挂起WAKE_IRQ, 由SEVONPEND产生事件唤醒WFE。
+/// 等待期间屏蔽中断, 由SEVONPEND让挂起的中断唤醒WFE, 记录时间后才执行中断,
+/// 因此中断(包括控制循环所在的中断执行器)的执行时间不计入空闲。
+pub struct Executor {
+    inner: raw::Executor,
+    not_send: PhantomData<*mut ()>,
+}
+
+impl Executor {
+    /// 调用run前需要使能DWT周期计数器和SCB.SEVONPEND
+    pub fn new() -> Self {
+        WAKE_IRQ.disable();
+        Self {
+            inner: raw::Executor::new(WAKE_IRQ.number() as usize as *mut ()),
+            not_send: PhantomData,
+        }
+    }
+
+    pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+        init(self.inner.spawner());
+        loop {
+            // SAFETY: poll只在线程模式的这个循环中调用
+            unsafe { self.inner.poll() };
+            interrupt::free(|_| {
+                // 清除挂起位, 之后再挂起才会产生事件; 清除前挂起产生的事件保留在事件寄存器中
+                NVIC::unpend(WAKE_IRQ);
+                let start = DWT::cycle_count();
+                asm::wfe();
+                IDLE_CYCLES.fetch_add(DWT::cycle_count().wrapping_sub(start), Ordering::Relaxed);
+            });
+        }
+    }
+}
@@ -1,4 +0,0 @@
-pub const _SQRT3_2: f32 = 0.86602540378;
-pub const _PI_2: f32 = 1.57079632679;
-pub const _2PI: f32 = 6.28318530718;
-pub const _3PI_2: f32 = 4.71238898038;
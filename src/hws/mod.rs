@@ -1 +1,4 @@
 pub mod drv8323rs;
+pub mod nfault;
+pub mod power_monitor;
+pub mod try_spi;
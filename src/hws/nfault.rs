@@ -0,0 +1,76 @@
+use embassy_stm32::{
+    gpio::{Input, Pull},
+    interrupt::{self, InterruptExt, Priority},
+    pac,
+    peripherals::PC9,
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+
+use crate::drivers::tim1;
+
+// nFAULT(PC9)对应的EXTI线
+const NFAULT_LINE: u32 = 9;
+// SYSCFG_EXTICR中GPIOC的端口编号
+const PORT_C: u32 = 2;
+
+/// EXTI9_5中断中检测到nFAULT下降沿
+static NFAULT_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// nFAULT下降沿中断, 直接清除BDTR.MOE关闭PWM输出, 不等待执行器调度
+#[interrupt]
+fn EXTI9_5() {
+    let exti = pac::EXTI;
+    if exti.pr(0).read().0 & (1 << NFAULT_LINE) == 0 {
+        return;
+    }
+    // 写1清除挂起位
+    exti.pr(0).write(|w| w.0 = 1 << NFAULT_LINE);
+    tim1::force_outputs_off();
+    NFAULT_SIGNAL.signal(());
+}
+
+/// DRV8323 nFAULT输入
+///
+/// 下降沿由EXTI9_5中断处理, 中断优先级高于控制循环, 故障后几微秒内关闭PWM输出;
+/// 读取故障寄存器和上报由drv8323_fault_task完成。
+pub struct NFault {
+    pin: Input<'static>,
+}
+
+impl NFault {
+    pub fn new(pin: PC9) -> Self {
+        let pin = Input::new(pin, Pull::Up);
+
+        // SYSCFGEN
+        pac::RCC.apb2enr().modify(|w| w.0 |= 1);
+        // EXTICR3的EXTI9字段选择GPIOC
+        pac::SYSCFG.exticr(2).modify(|w| {
+            w.0 &= !(0xF << 4);
+            w.0 |= PORT_C << 4;
+        });
+        let exti = pac::EXTI;
+        exti.ftsr(0).modify(|w| w.0 |= 1 << NFAULT_LINE);
+        exti.rtsr(0).modify(|w| w.0 &= !(1 << NFAULT_LINE));
+        exti.pr(0).write(|w| w.0 = 1 << NFAULT_LINE);
+        exti.imr(0).modify(|w| w.0 |= 1 << NFAULT_LINE);
+
+        interrupt::EXTI9_5.set_priority(Priority::P4);
+        // SAFETY: EXTI9_5中断处理函数只访问EXTI挂起位、TIM1 BDTR和Signal
+        unsafe { interrupt::EXTI9_5.enable() };
+
+        Self { pin }
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.pin.is_low()
+    }
+
+    /// 等待nFAULT为低电平, 已经为低时立即返回
+    pub async fn wait_for_low(&mut self) {
+        NFAULT_SIGNAL.reset();
+        if self.pin.is_low() {
+            return;
+        }
+        NFAULT_SIGNAL.wait().await;
+    }
+}
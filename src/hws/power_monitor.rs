@@ -0,0 +1,103 @@
+use embassy_futures::yield_now;
+use embassy_stm32::{gpio::Flex, pac};
+
+use crate::MonitorResources;
+use caw_foc_core::monitor::{internal_temperature, MonitorConfig};
+
+const ADC_RESOLUTION: f32 = 4095.0;
+
+// VBUS/NTC/内部温度传感器对应的ADC1通道
+const VBUS_CHANNEL: u32 = 4;
+const NTC_CHANNEL: u32 = 6;
+const TEMP_CHANNEL: u32 = 16;
+
+// 内部温度传感器的出厂校准值地址(30°C/130°C)
+const TS_CAL1: *const u16 = 0x1FFF_75A8 as *const u16;
+const TS_CAL2: *const u16 = 0x1FFF_75CA as *const u16;
+
+/// CawDrive的VBUS分压和NTC参数
+pub const MONITOR_CONFIG: MonitorConfig = MonitorConfig {
+    adc_vref: 3.3,
+    vbus_r_top: 47_000.0,
+    vbus_r_bottom: 3_300.0,
+    ntc_r_pullup: 10_000.0,
+    ntc_r25: 10_000.0,
+    ntc_beta: 3380.0,
+    filter_tf: 0.01,
+};
+
+/// 一次规则组转换的结果
+pub struct AdcSample {
+    pub vbus_voltage: f32,    // VBUS分压后的电压(V)
+    pub ntc_voltage: f32,     // NTC分压电压(V)
+    pub mcu_temperature: f32, // MCU内部温度(°C)
+}
+
+/// 使用ADC1规则组采样VBUS、NTC和内部温度传感器
+///
+/// ADC1由LowsideCurrentSense初始化并使能, 注入组用于电流采样,
+/// 规则组由软件触发, 注入转换可以打断规则转换, 两者互不影响。
+pub struct AdcMonitor {
+    _pins: [Flex<'static>; 2],
+    ts_cal1: u16,
+    ts_cal2: u16,
+}
+
+impl AdcMonitor {
+    pub fn new(r: MonitorResources) -> Self {
+        let mut vbus = Flex::new(r.vbus);
+        let mut ntc = Flex::new(r.ntc);
+        vbus.set_as_analog();
+        ntc.set_as_analog();
+
+        // VSENSESEL, 连接内部温度传感器到ADC1_IN16
+        pac::ADC12_COMMON.ccr().modify(|w| w.0 |= 1 << 23);
+
+        let regs = pac::ADC1;
+        // VBUS和NTC采样时间 92.5 cycles
+        regs.smpr(0).modify(|w| {
+            for ch in [VBUS_CHANNEL, NTC_CHANNEL] {
+                w.0 &= !(0b111 << (ch * 3));
+                w.0 |= 0b101 << (ch * 3);
+            }
+        });
+        // 温度传感器需要至少5us的采样时间, 使用640.5 cycles
+        regs.smpr(1)
+            .modify(|w| w.0 |= 0b111 << ((TEMP_CHANNEL - 10) * 3));
+        // L = 2(3次转换), 软件触发
+        regs.sqr1().modify(|w| {
+            w.0 = 0b10 | (VBUS_CHANNEL << 6) | (NTC_CHANNEL << 12) | (TEMP_CHANNEL << 18);
+        });
+
+        // SAFETY: 出厂校准值位于只读的系统存储区
+        let (ts_cal1, ts_cal2) = unsafe { (TS_CAL1.read_volatile(), TS_CAL2.read_volatile()) };
+
+        Self {
+            _pins: [vbus, ntc],
+            ts_cal1,
+            ts_cal2,
+        }
+    }
+
+    /// 启动一次规则组转换并依次读取3个通道
+    pub async fn sample(&mut self) -> AdcSample {
+        let regs = pac::ADC1;
+        let mut raw = [0u16; 3];
+        // ADSTART
+        regs.cr().modify(|w| w.0 |= 1 << 2);
+        for r in raw.iter_mut() {
+            // 等待EOC, 读取DR后自动清除
+            while regs.isr().read().0 & (1 << 2) == 0 {
+                yield_now().await;
+            }
+            *r = (regs.dr().read().0 & 0xFFFF) as u16;
+        }
+
+        let vref = MONITOR_CONFIG.adc_vref;
+        AdcSample {
+            vbus_voltage: raw[0] as f32 * vref / ADC_RESOLUTION,
+            ntc_voltage: raw[1] as f32 * vref / ADC_RESOLUTION,
+            mcu_temperature: internal_temperature(raw[2], vref, self.ts_cal1, self.ts_cal2),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use core::fmt::Debug;
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
+use embassy_time::Timer;
+use embedded_hal::{
+    digital::OutputPin,
+    spi::{Error, ErrorKind},
+};
+use embedded_hal_async::spi::{self, Operation, SpiBus};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrySpiError<BUS, CS> {
+    /// 总线正被其他设备占用
+    Busy,
+    Spi(BUS),
+    Cs(CS),
+}
+
+impl<BUS: Error, CS: Debug> Error for TrySpiError<BUS, CS> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            TrySpiError::Spi(e) => e.kind(),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// 共享总线上不等待总线锁的SpiDevice
+///
+/// 与embassy_embedded_hal的SpiDevice相同, 但总线被占用时立即返回Busy,
+/// 控制循环读取编码器时不会被线程模式中正在访问DRV8323的任务阻塞。
+/// set_wait(true)后与普通SpiDevice一样等待总线, 用于不要求实时性的传感器对齐。
+pub struct TrySpiDevice<'a, M: RawMutex, BUS, CS> {
+    bus: &'a Mutex<M, BUS>,
+    cs: CS,
+    wait: bool,
+}
+
+impl<'a, M: RawMutex, BUS, CS> TrySpiDevice<'a, M, BUS, CS> {
+    pub fn new(bus: &'a Mutex<M, BUS>, cs: CS) -> Self {
+        Self {
+            bus,
+            cs,
+            wait: false,
+        }
+    }
+
+    pub fn set_wait(&mut self, wait: bool) {
+        self.wait = wait;
+    }
+}
+
+impl<M, BUS, CS> spi::ErrorType for TrySpiDevice<'_, M, BUS, CS>
+where
+    M: RawMutex,
+    BUS: spi::ErrorType,
+    CS: OutputPin,
+{
+    type Error = TrySpiError<BUS::Error, CS::Error>;
+}
+
+impl<M, BUS, CS, Word> spi::SpiDevice<Word> for TrySpiDevice<'_, M, BUS, CS>
+where
+    M: RawMutex,
+    BUS: SpiBus<Word>,
+    CS: OutputPin,
+    Word: Copy + 'static,
+{
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, Word>],
+    ) -> Result<(), Self::Error> {
+        let mut bus = if self.wait {
+            self.bus.lock().await
+        } else {
+            self.bus.try_lock().map_err(|_| TrySpiError::Busy)?
+        };
+        self.cs.set_low().map_err(TrySpiError::Cs)?;
+
+        let op_res = async {
+            for op in operations {
+                match op {
+                    Operation::Read(buf) => bus.read(buf).await?,
+                    Operation::Write(buf) => bus.write(buf).await?,
+                    Operation::Transfer(read, write) => bus.transfer(read, write).await?,
+                    Operation::TransferInPlace(buf) => bus.transfer_in_place(buf).await?,
+                    Operation::DelayNs(ns) => {
+                        bus.flush().await?;
+                        Timer::after_nanos(*ns as u64).await;
+                    }
+                }
+            }
+            Ok::<(), BUS::Error>(())
+        }
+        .await;
+
+        // 出错时也要释放片选
+        let flush_res = bus.flush().await;
+        let cs_res = self.cs.set_high();
+        op_res.map_err(TrySpiError::Spi)?;
+        flush_res.map_err(TrySpiError::Spi)?;
+        cs_res.map_err(TrySpiError::Cs)?;
+        Ok(())
+    }
+}
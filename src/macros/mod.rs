@@ -1,2 +1 @@
 pub mod assign_resources;
-pub mod helper;
@@ -1,37 +1,89 @@
 #![no_std]
 #![no_main]
 
-mod comm;
+mod config;
+mod current_sense;
 mod drivers;
-mod fast_math;
+mod executor;
 mod hws;
 mod macros;
-mod motor;
 mod resources;
 mod tasks;
 
 use crate::{hws::drv8323rs::*, Drv8323Resources};
+use caw_foc_core::{
+    axis::AxisRequest,
+    motor::{ControlType, Motor},
+    sensors::as5047p::AS5047P,
+};
+use config::{storage::ConfigStorage, DriveConfig};
+use current_sense::lowside::LowsideCurrentSense;
 use defmt::*;
 use drivers::{pwmx3::PWMX3, pwmx6::PWMX6};
 use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
-use embassy_executor::Spawner;
+use embassy_executor::{InterruptExecutor, Spawner};
 use embassy_stm32::{
+    flash::Flash,
     gpio::{Level, Output, Speed},
+    interrupt::{self, InterruptExt, Priority},
     time::Hertz,
 };
-use embassy_time::Timer;
-use hws::drv8323rs::DRV8232RS;
-use motor::{ControlType, Motor};
+use embassy_time::{Delay, Timer};
+use executor::Executor;
+use hws::{drv8323rs::DRV8232RS, nfault::NFault, power_monitor::AdcMonitor, try_spi::TrySpiDevice};
 use resources::*;
+use static_cell::StaticCell;
 use tasks::{
     can::{can2_task, can3_task},
+    config::config_task,
+    control::control_task,
+    drv8323::{drv8323_fault_task, FaultPolicy},
+    messages::{power_readings, AXIS_REQUEST_CHANNEL},
+    monitor::power_monitor_task,
+    profiling::profiling_task,
     state::check_state_task,
     usart::usart1_task,
 };
 use {defmt_rtt as _, panic_probe as _};
 
-#[embassy_executor::main]
-async fn main(spawner: Spawner) {
+/// 栅极驱动故障后最多自动重试3次, 恢复后5秒内无故障时重新计数
+const DRV_FAULT_POLICY: FaultPolicy = FaultPolicy::AutoRetry {
+    max_retries: 3,
+    backoff_ms: 100,
+    hold_off_ms: 5000,
+};
+
+/// 控制循环所在的执行器, 由UART4中断驱动, 优先级高于线程模式的主执行器
+static EXECUTOR_HIGH: InterruptExecutor = InterruptExecutor::new();
+
+#[interrupt]
+unsafe fn UART4() {
+    EXECUTOR_HIGH.on_interrupt()
+}
+
+/// DRV8323配置失败时拉低EN_GATE关闭栅极驱动并停机
+fn drv_failed(enable: &mut Output<'static>, e: Drv8323Error) -> ! {
+    enable.set_low();
+    defmt::panic!("drv8323 configure failed: {:?}", e);
+}
+
+#[cortex_m_rt::entry]
+fn main() -> ! {
+    // 使能DWT CYCCNT, 用于控制循环计时和CPU负载统计
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    cp.DCB.enable_trace();
+    cp.DWT.enable_cycle_counter();
+    // 屏蔽中断时挂起的中断也能唤醒WFE, 见executor::Executor
+    cp.SCB.set_sevonpend();
+
+    static EXECUTOR: StaticCell<Executor> = StaticCell::new();
+    EXECUTOR
+        .init(Executor::new())
+        .run(|spawner| spawner.must_spawn(main_task(spawner)))
+}
+
+#[embassy_executor::task]
+async fn main_task(spawner: Spawner) {
     let mut config: embassy_stm32::Config = Default::default();
     {
         use embassy_stm32::rcc::*;
@@ -50,6 +102,7 @@ async fn main(spawner: Spawner) {
             divr: Some(PllRDiv::DIV2), // 系统时钟
         });
         config.rcc.mux.fdcansel = mux::Fdcansel::PLL1_Q;
+        config.rcc.mux.adc12sel = mux::Adcsel::SYS;
         config.rcc.sys = Sysclk::PLL1_R;
         config.rcc.ahb_pre = AHBPrescaler::DIV1;
         config.rcc.apb1_pre = APBPrescaler::DIV1;
@@ -64,6 +117,12 @@ async fn main(spawner: Spawner) {
 
     info!("[ CawFOC ]");
 
+    let mut storage = ConfigStorage::new(Flash::new_blocking(r.flash.flash));
+    let cfg = storage.load().unwrap_or_else(|| {
+        warn!("no valid config in flash, using defaults");
+        DriveConfig::default()
+    });
+
     // can bus configure
     let mut can_stb = Output::new(p.PD2, Level::High, Speed::High);
     can_stb.set_low();
@@ -72,46 +131,116 @@ async fn main(spawner: Spawner) {
     let drv_spi = init_spi3(r.spi3).await;
     let drv_nss = Output::new(p.PA15, Level::High, Speed::Low);
     let drv_spi_dev = SpiDevice::new(drv_spi, drv_nss);
+    // 编码器与DRV8323共用SPI3, 总线被占用时读取立即失败, 不阻塞控制循环
+    let sensor_spi_dev = TrySpiDevice::new(drv_spi, sensor_nss);
+    let mut encoder = AS5047P::new(sensor_spi_dev);
 
-    let mut drv = DRV8232RS::new(drv_spi_dev).await;
+    let mut drv = DRV8232RS::new(drv_spi_dev, Delay);
     Timer::after_millis(10).await;
     let mut enable = Output::new(r.drv8323.enable, Level::Low, Speed::Low);
-    let mut cal = Output::new(r.drv8323.cal, Level::High, Speed::Low);
+    // CAL引脚保持低电平, CSA通过SPI校准
+    let _cal = Output::new(r.drv8323.cal, Level::Low, Speed::Low);
     Timer::after_millis(10).await;
     enable.set_high();
-    cal.set_low();
-    Timer::after_millis(10).await;
-    drv.calibrate().await;
-    Timer::after_millis(10).await;
-    drv.write_dcr(0x0, DIS_GDF_DIS, 0x0, PWM_MODE_3X, 0x0, 0x0, 0x0, 0x0, 0x1)
-        .await;
     Timer::after_millis(10).await;
-    drv.write_csacr(0x0, 0x1, 0x0, CSA_GAIN_40, 0x0, 0x1, 0x1, 0x1, SEN_LVL_1_0)
-        .await;
-    Timer::after_millis(10).await;
-    drv.write_csacr(0x0, 0x1, 0x0, CSA_GAIN_40, 0x1, 0x0, 0x0, 0x0, SEN_LVL_1_0)
-        .await;
-    Timer::after_millis(10).await;
-    drv.write_ocpcr(
-        TRETRY_50US,
-        DEADTIME_50NS,
-        OCP_NONE,
-        OCP_DEG_8US,
-        VDS_LVL_1_88,
-    )
-    .await;
-    drv.dbg_reg_val().await;
-    drv.enable_gd().await;
+    if let Err(e) = drv.apply(&cfg.drv).await {
+        drv_failed(&mut enable, e);
+    }
+    // 写入增益后再校准CSA偏置
+    if let Err(e) = drv.calibrate_csa(CsaCalibration::Manual).await {
+        drv_failed(&mut enable, e);
+    }
+    let _ = drv.dbg_reg_val().await;
+    // 上电后处于Idle, 栅极驱动由状态机使能
+    if let Err(e) = drv.disable_gd().await {
+        drv_failed(&mut enable, e);
+    }
+    let nfault = NFault::new(r.drv8323.fault);
+    spawner
+        .spawn(drv8323_fault_task(drv, nfault, DRV_FAULT_POLICY))
+        .unwrap();
     Timer::after_millis(500).await;
 
-    let mut motor = Motor::new(7, 1, PWMX3::new(r.pwm_tim, 12.0, 6.0), ControlType::None);
+    let mut motor = Motor::new(
+        cfg.pole_pairs,
+        cfg.sensor_direction,
+        PWMX3::new(r.pwm_tim, cfg.voltage_power_supply, cfg.voltage_limit),
+        encoder,
+        ControlType::None,
+    );
+    motor.set_velocity_limit(cfg.velocity_limit);
+    motor.pid_velocity.p = cfg.pid_velocity_p;
+    motor.pid_velocity.i = cfg.pid_velocity_i;
+    motor.pid_velocity.d = cfg.pid_velocity_d;
+    motor.pid_angle.p = cfg.pid_angle_p;
+    motor.pid_current_d.p = cfg.pid_current_p;
+    motor.pid_current_d.i = cfg.pid_current_i;
+    motor.pid_current_q.p = cfg.pid_current_p;
+    motor.pid_current_q.i = cfg.pid_current_i;
+    motor.disable();
+
+    let csa_gain = cfg.drv.csa_control.csa_gain.gain();
+    let mut current_sense = LowsideCurrentSense::new(r.current_sense, cfg.shunt_resistor, csa_gain);
+    // DRV8323已置位COAST, 所有MOSFET处于高阻态, 相电流为0, 测量电流采样零点
+    if let Err(e) = current_sense.calibrate_offsets(4000).await {
+        enable.set_low();
+        defmt::panic!("current sense calibration failed: {:?}", e);
+    }
+    info!(
+        "current sense offsets: {} {} {}",
+        current_sense.offset_a, current_sense.offset_b, current_sense.offset_c
+    );
+
+    // ADC1由电流采样使能后再启动母线电压和温度监测
+    spawner
+        .spawn(power_monitor_task(AdcMonitor::new(r.monitor)))
+        .unwrap();
+    Timer::after_millis(50).await;
+    let power = power_readings();
+    info!("power: {:?}", power);
+    motor.set_bus_voltage(power.vbus);
+
+    // 初始化不在控制循环中, 可以等待总线
+    motor.sensor.spi_mut().set_wait(true);
+    if let Err(e) = motor.init().await {
+        error!("sensor init failed: {:?}", e);
+    }
+    motor.sensor.spi_mut().set_wait(false);
+    if cfg.sensor_aligned {
+        motor.set_sensor_alignment(cfg.sensor_direction, cfg.zero_electric_angle);
+    } else {
+        // 未对齐时上电后自动校准
+        let _ = AXIS_REQUEST_CHANNEL.try_send(AxisRequest::Calibrate);
+    }
 
-    spawner.spawn(can2_task(spawner, r.can2)).unwrap();
-    spawner.spawn(can3_task(spawner, r.can3)).unwrap();
+    spawner
+        .spawn(can2_task(
+            spawner,
+            r.can2,
+            cfg.can_bitrate,
+            cfg.can_data_bitrate,
+        ))
+        .unwrap();
+    spawner
+        .spawn(can3_task(
+            spawner,
+            r.can3,
+            cfg.can_bitrate,
+            cfg.can_data_bitrate,
+        ))
+        .unwrap();
+    spawner.spawn(config_task(storage, cfg)).unwrap();
     spawner.spawn(usart1_task(spawner, r.usart1)).unwrap();
     spawner.spawn(check_state_task(spawner, r.state)).unwrap();
-    loop {
-        motor.step(-20.0);
-        Timer::after_ticks(1).await;
-    }
+    spawner.spawn(profiling_task()).unwrap();
+
+    // 控制循环运行在高优先级执行器中, 由ADC注入转换完成中断按固定频率触发
+    interrupt::UART4.set_priority(Priority::P6);
+    let spawner_high = EXECUTOR_HIGH.start(interrupt::UART4);
+    spawner_high
+        .spawn(control_task(motor, current_sense, cfg))
+        .unwrap();
+
+    // 保持EN_GATE、CAL和CAN_STB引脚的状态
+    core::future::pending::<()>().await;
 }
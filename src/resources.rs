@@ -1,6 +1,13 @@
-use crate::assign_resources;
-use embassy_stm32::{mode::Async, peripherals, spi, time::Hertz};
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use crate::{
+    assign_resources,
+    drivers::pwmx3::PWMX3,
+    hws::{drv8323rs::DRV8232RS, try_spi::TrySpiDevice},
+};
+use caw_foc_core::{motor::Motor, sensors::as5047p::AS5047P};
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_stm32::{gpio::Output, mode::Async, peripherals, spi, time::Hertz};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::Delay;
 use static_cell::StaticCell;
 
 assign_resources! {
@@ -42,6 +49,19 @@ assign_resources! {
         tim1_ch2n:PB14,
         tim1_ch3n:PB15,
     },
+    current_sense: CurrentSenseResources {
+        adc: ADC1,
+        soa: PA0,
+        sob: PA1,
+        soc: PA2,
+    },
+    monitor: MonitorResources {
+        vbus: PA3,
+        ntc: PC0,
+    },
+    flash: FlashResources {
+        flash: FLASH,
+    },
     drv8323: Drv8323Resources {
         cal: PC7,
         enable: PC8,
@@ -51,7 +71,18 @@ assign_resources! {
 
 /// Initialize SPI3 BUS
 /// Supports multi-task sharing
-pub type Spi3Bus = Mutex<NoopRawMutex, spi::Spi<'static, Async>>;
+///
+/// 编码器在中断执行器的控制循环中读取, DRV8323在线程模式的任务中访问,
+/// 跨执行器共享必须使用CriticalSectionRawMutex。
+pub type Spi3Bus = Mutex<CriticalSectionRawMutex, spi::Spi<'static, Async>>;
+
+/// SPI3总线上的设备
+pub type Spi3Device =
+    SpiDevice<'static, CriticalSectionRawMutex, spi::Spi<'static, Async>, Output<'static>>;
+
+/// SPI3总线上的编码器, 控制循环中不等待总线锁
+pub type EncoderSpiDevice =
+    TrySpiDevice<'static, CriticalSectionRawMutex, spi::Spi<'static, Async>, Output<'static>>;
 
 pub async fn init_spi3(r: Spi3Resources) -> &'static Spi3Bus {
     let mut config = spi::Config::default();
@@ -62,3 +93,9 @@ pub async fn init_spi3(r: Spi3Resources) -> &'static Spi3Bus {
     static SPI_BUS: StaticCell<Spi3Bus> = StaticCell::new();
     SPI_BUS.init(Mutex::new(spi))
 }
+
+/// 挂在SPI3总线上的DRV8323
+pub type Drv8323 = DRV8232RS<Spi3Device, Delay>;
+
+/// 控制循环中的电机
+pub type DriveMotor = Motor<PWMX3, AS5047P<EncoderSpiDevice>>;
@@ -1,9 +1,27 @@
 use defmt::*;
 use embassy_executor::Spawner;
+use embassy_futures::select::{select3, Either3};
 use embassy_stm32::peripherals::*;
 use embassy_stm32::{bind_interrupts, can};
 
+use embassy_time::{Duration, Ticker};
+
+use super::messages::{
+    axis_status, power_readings, profile_report, Commands, AXIS_REQUEST_CHANNEL, CAN_WRITE_SIGNAL,
+    CONFIG_REQUEST_CHANNEL,
+};
 use crate::resources::{Can2Resources, Can3Resources};
+use caw_foc_core::comm::{
+    decode_axis_request, decode_config_request, encode_axis_status, encode_faults, encode_power,
+    encode_profile_loop, encode_profile_sections, CAN_ID_AXIS_REQUEST, CAN_ID_AXIS_STATUS,
+    CAN_ID_CONFIG_REQUEST, CAN_ID_FAULTS, CAN_ID_POWER, CAN_ID_PROFILE_LOOP,
+    CAN_ID_PROFILE_SECTIONS,
+};
+
+/// 母线电压、温度和状态机状态的上报周期(ms)
+const POWER_REPORT_MS: u64 = 100;
+/// 性能统计的上报间隔, 以POWER_REPORT_MS计
+const PROFILE_REPORT_INTERVAL: u32 = 10;
 
 bind_interrupts!(pub struct Irqs {
     FDCAN2_IT0 => can::IT0InterruptHandler<FDCAN2>;
@@ -13,7 +31,7 @@ bind_interrupts!(pub struct Irqs {
 });
 
 #[embassy_executor::task]
-pub async fn can2_task(_spawner: Spawner, r: Can2Resources) {
+pub async fn can2_task(_spawner: Spawner, r: Can2Resources, bitrate: u32, data_bitrate: u32) {
     let mut can2 = can::CanConfigurator::new(r.fdcan, r.rx_pin, r.tx_pin, Irqs);
     can2.properties().set_extended_filter(
         can::filter::ExtendedFilterSlot::_0,
@@ -23,27 +41,83 @@ pub async fn can2_task(_spawner: Spawner, r: Can2Resources) {
         can::filter::StandardFilterSlot::_0,
         can::filter::StandardFilter::accept_all_into_fifo0(),
     );
-    can2.set_bitrate(500_000);
-    can2.set_fd_data_bitrate(5_000_000, false);
+    can2.set_bitrate(bitrate);
+    can2.set_fd_data_bitrate(data_bitrate, false);
     let mut can2 = can2.start(can::OperatingMode::NormalOperationMode);
+    let mut power_ticker = Ticker::every(Duration::from_millis(POWER_REPORT_MS));
+    let mut report_count = 0u32;
 
     loop {
-        match can2.read_fd().await {
-            Ok(envelope) => {
+        match select3(can2.read_fd(), CAN_WRITE_SIGNAL.wait(), power_ticker.next()).await {
+            Either3::First(Ok(envelope)) => {
                 let (_ts, rx_frame) = (envelope.ts, envelope.frame);
-                info!(
-                    "Rx: {} {:02x}",
-                    rx_frame.header().len(),
-                    rx_frame.data()[0..rx_frame.header().len() as usize],
+                let data = &rx_frame.data()[0..rx_frame.header().len() as usize];
+                match rx_frame.header().id() {
+                    embedded_can::Id::Standard(id) if id.as_raw() == CAN_ID_AXIS_REQUEST => {
+                        match decode_axis_request(data) {
+                            Some(request) => {
+                                if AXIS_REQUEST_CHANNEL.try_send(request).is_err() {
+                                    warn!("axis request channel full, {:?} dropped", request);
+                                }
+                            }
+                            None => warn!("invalid axis request: {:02x}", data),
+                        }
+                    }
+                    embedded_can::Id::Standard(id) if id.as_raw() == CAN_ID_CONFIG_REQUEST => {
+                        match decode_config_request(data) {
+                            Some(request) => {
+                                if CONFIG_REQUEST_CHANNEL.try_send(request).is_err() {
+                                    warn!("config request channel full, {:?} dropped", request);
+                                }
+                            }
+                            None => warn!("invalid config request: {:02x}", data),
+                        }
+                    }
+                    _ => info!("Rx: {} {:02x}", rx_frame.header().len(), data),
+                }
+            }
+            Either3::First(Err(err)) => error!("Error in frame {:?}", err),
+            Either3::Second(Commands::CanTxFaults(bits)) => {
+                let frame =
+                    can::frame::Frame::new_standard(CAN_ID_FAULTS, &encode_faults(bits)).unwrap();
+                can2.write(&frame).await;
+            }
+            Either3::Second(_) => {}
+            Either3::Third(()) => {
+                let frame =
+                    can::frame::Frame::new_standard(CAN_ID_POWER, &encode_power(&power_readings()))
+                        .unwrap();
+                can2.write(&frame).await;
+                let frame = can::frame::Frame::new_standard(
+                    CAN_ID_AXIS_STATUS,
+                    &encode_axis_status(&axis_status()),
                 )
+                .unwrap();
+                can2.write(&frame).await;
+
+                report_count += 1;
+                if report_count % PROFILE_REPORT_INTERVAL == 0 {
+                    let report = profile_report();
+                    let frame = can::frame::Frame::new_standard(
+                        CAN_ID_PROFILE_LOOP,
+                        &encode_profile_loop(&report),
+                    )
+                    .unwrap();
+                    can2.write(&frame).await;
+                    let frame = can::frame::Frame::new_standard(
+                        CAN_ID_PROFILE_SECTIONS,
+                        &encode_profile_sections(&report),
+                    )
+                    .unwrap();
+                    can2.write(&frame).await;
+                }
             }
-            Err(err) => error!("Error in frame {:?}", err),
         }
     }
 }
 
 #[embassy_executor::task]
-pub async fn can3_task(_spawner: Spawner, r: Can3Resources) {
+pub async fn can3_task(_spawner: Spawner, r: Can3Resources, bitrate: u32, data_bitrate: u32) {
     let mut can3 = can::CanConfigurator::new(r.fdcan, r.rx_pin, r.tx_pin, Irqs);
     can3.properties().set_extended_filter(
         can::filter::ExtendedFilterSlot::_0,
@@ -53,8 +127,8 @@ pub async fn can3_task(_spawner: Spawner, r: Can3Resources) {
         can::filter::StandardFilterSlot::_0,
         can::filter::StandardFilter::accept_all_into_fifo0(),
     );
-    can3.set_bitrate(500_000);
-    can3.set_fd_data_bitrate(5_000_000, false);
+    can3.set_bitrate(bitrate);
+    can3.set_fd_data_bitrate(data_bitrate, false);
     let mut can3 = can3.start(can::OperatingMode::NormalOperationMode);
 
     loop {
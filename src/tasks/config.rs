@@ -0,0 +1,52 @@
+use defmt::*;
+
+use super::messages::{Commands, CONFIG_REQUEST_CHANNEL, USART_WRITE_SIGNAL};
+use crate::config::{storage::ConfigStorage, DriveConfig};
+use caw_foc_core::config::ConfigRequest;
+
+/// 持有当前配置, 处理参数修改和保存请求
+///
+/// 修改的参数在保存并重启后生效。擦写Flash期间CPU会被阻塞, 应在电机停止时请求保存。
+#[embassy_executor::task]
+pub async fn config_task(mut storage: ConfigStorage, mut cfg: DriveConfig) {
+    loop {
+        let request = CONFIG_REQUEST_CHANNEL.receive().await;
+        let ok = match request {
+            ConfigRequest::Set { param, value } => match cfg.set(param, value) {
+                Ok(()) => {
+                    info!("config: {:?} = {}", param, value);
+                    true
+                }
+                Err(param) => {
+                    warn!("config: invalid {:?} = {}", param, value);
+                    false
+                }
+            },
+            ConfigRequest::Save => save(&mut storage, &cfg),
+            ConfigRequest::SensorAlignment {
+                sensor_direction,
+                zero_electric_angle,
+            } => {
+                cfg.sensor_aligned = true;
+                cfg.sensor_direction = sensor_direction;
+                cfg.zero_electric_angle = zero_electric_angle;
+                save(&mut storage, &cfg);
+                continue;
+            }
+        };
+        USART_WRITE_SIGNAL.signal(Commands::UsartTxStr(if ok { "OK\r\n" } else { "ERR\r\n" }));
+    }
+}
+
+fn save(storage: &mut ConfigStorage, cfg: &DriveConfig) -> bool {
+    match storage.save(cfg) {
+        Ok(()) => {
+            info!("config saved");
+            true
+        }
+        Err(e) => {
+            error!("config save failed: {:?}", e);
+            false
+        }
+    }
+}
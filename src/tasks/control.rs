@@ -0,0 +1,233 @@
+use core::sync::atomic::Ordering;
+use cortex_m::peripheral::DWT;
+use defmt::*;
+use embassy_time::{Instant, Timer};
+
+use super::messages::{
+    power_readings, Commands, Events, AXIS_REQUEST_CHANNEL, AXIS_STATUS, CONFIG_REQUEST_CHANNEL,
+    DRV_CLEAR_SIGNAL, DRV_FAULTS, DRV_GATE_SIGNAL, EVENT_CHANNEL, LOOP_TIMING, MOTOR_PROFILE,
+    USART_WRITE_SIGNAL,
+};
+use crate::{
+    config::DriveConfig,
+    current_sense::lowside::{
+        LowsideCurrentSense, CONTROL_FREQUENCY_HZ, CONTROL_TICKS, CURRENT_SAMPLE_SIGNAL,
+    },
+    drivers::tim1,
+    DriveMotor,
+};
+use caw_foc_core::{
+    axis::{Axis, AxisRequest, AxisState},
+    config::ConfigRequest,
+    current_sense::base::CurrentSense,
+    protection::{ProtectionManager, ProtectionState, TripReason},
+    timing::LoopTiming,
+};
+
+/// 速度环、位置环和编码器读取相对电流环的分频系数, 20kHz下为2kHz
+///
+/// 编码器与drv8323_fault_task共用SPI3, 读取放在分频周期内, 其余周期不访问总线。
+/// 总线被占用时读取立即失败, 电角度继续按速度推算, 不等待总线。
+const VELOCITY_DECIMATION: u32 = 10;
+/// 系统时钟, DWT CYCCNT的计数频率
+pub const CPU_FREQUENCY_HZ: u32 = 160_000_000;
+
+/// 按状态机状态设置栅极驱动、PWM输出和控制模式, 并上报状态
+fn apply_axis_state(motor: &mut DriveMotor, axis: &Axis) {
+    if axis.output_enabled() {
+        // nFAULT中断清除的MOE在这里恢复, 故障清除前状态机不会进入需要输出的状态
+        tim1::enable_outputs();
+        DRV_GATE_SIGNAL.signal(true);
+        if !motor.enabled() {
+            motor.enable();
+        }
+    } else {
+        if motor.enabled() {
+            motor.disable();
+        }
+        DRV_GATE_SIGNAL.signal(false);
+    }
+    motor.set_control_type(axis.control_type());
+
+    let status = axis.status();
+    info!("axis: {:?}", status);
+    AXIS_STATUS.lock(|s| s.set(status));
+    USART_WRITE_SIGNAL.signal(Commands::UsartTxAxisStatus(status));
+}
+
+/// 保护跳闸: 进入Fault, 关闭输出并将栅极驱动置为COAST, 上报跳闸事件
+fn trip(motor: &mut DriveMotor, axis: &mut Axis, reason: TripReason) {
+    if axis.state() == AxisState::Fault {
+        return;
+    }
+    axis.fault(reason);
+    apply_axis_state(motor, axis);
+    if EVENT_CHANNEL
+        .try_send(Events::ProtectionTrip(reason))
+        .is_err()
+    {
+        warn!("event channel full, protection trip event dropped");
+    }
+}
+
+/// 传感器对齐后重新测量电流采样零点, 成功后保存对齐结果
+///
+/// 对齐约3秒, 期间每一步都评估保护, 跳闸时中止对齐, 由调用者进入Fault。
+async fn calibrate(
+    motor: &mut DriveMotor,
+    current_sense: &mut LowsideCurrentSense,
+    protection: &mut ProtectionManager,
+) -> bool {
+    let mut last = Instant::now();
+    let abort = || {
+        let now = Instant::now();
+        let dt = (now - last).as_micros() as f32 * 1e-6;
+        last = now;
+        let state = protection.update(
+            &power_readings(),
+            &current_sense.get_phase_currents(),
+            DRV_FAULTS.load(Ordering::Relaxed),
+            dt,
+        );
+        matches!(state, ProtectionState::Tripped(_))
+    };
+    // 对齐过程不要求实时性, 等待总线避免读取失败
+    motor.sensor.spi_mut().set_wait(true);
+    let result = motor.align_sensor_with(abort).await;
+    motor.sensor.spi_mut().set_wait(false);
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            error!("sensor align failed: {:?}", e);
+            return false;
+        }
+    };
+    info!("sensor aligned: {:?}", result);
+    // align_sensor结束时占空比为0, 三相下桥臂导通并等待200ms, 相电流已衰减到0
+    if let Err(e) = current_sense.calibrate_offsets(4000).await {
+        error!("current sense calibration failed: {:?}", e);
+        return false;
+    }
+    let request = ConfigRequest::SensorAlignment {
+        sensor_direction: result.sensor_direction,
+        zero_electric_angle: result.zero_electric_angle,
+    };
+    if CONFIG_REQUEST_CHANNEL.try_send(request).is_err() {
+        warn!("config request channel full, sensor alignment not saved");
+    }
+    true
+}
+
+/// DWT周期数换算为微秒
+pub fn cycles_to_us(cycles: u32) -> f32 {
+    cycles as f32 * 1e6 / CPU_FREQUENCY_HZ as f32
+}
+
+/// 电流/FOC控制循环, 运行在高优先级中断执行器中
+///
+/// 每次ADC注入转换完成(按CONTROL_DECIMATION分频)执行一次step,
+/// 使用DWT CYCCNT统计循环周期和执行时间, 每秒写入LOOP_TIMING和MOTOR_PROFILE。
+#[embassy_executor::task]
+pub async fn control_task(
+    mut motor: DriveMotor,
+    mut current_sense: LowsideCurrentSense,
+    cfg: DriveConfig,
+) {
+    let dt = 1.0 / CONTROL_FREQUENCY_HZ as f32;
+    motor.set_loop_period(dt);
+    motor.set_velocity_decimation(VELOCITY_DECIMATION);
+    motor.set_cycle_counter(DWT::cycle_count);
+
+    let mut axis = Axis::new(cfg.sensor_aligned);
+    let mut protection = ProtectionManager::new(cfg.protection);
+    apply_axis_state(&mut motor, &axis);
+
+    let mut timing = LoopTiming::new();
+    let mut last_start: Option<u32> = None;
+    let mut last_tick = CONTROL_TICKS.load(Ordering::Relaxed);
+    loop {
+        CURRENT_SAMPLE_SIGNAL.wait().await;
+        let start = DWT::cycle_count();
+        let tick = CONTROL_TICKS.load(Ordering::Relaxed);
+        // 两次循环之间发出了多次触发, 说明上次执行超时
+        if tick.wrapping_sub(last_tick) > 1 {
+            timing.overruns += 1;
+        }
+        last_tick = tick;
+        if let Some(last) = last_start {
+            timing.period.record(start.wrapping_sub(last));
+        }
+        last_start = Some(start);
+
+        if let Ok(request) = AXIS_REQUEST_CHANNEL.try_receive() {
+            let result = match request {
+                // DRV8323仍有锁存的故障时先由drv8323_fault_task清除, 成功后它会重新发送ClearFault
+                AxisRequest::ClearFault if DRV_FAULTS.load(Ordering::Relaxed) != 0 => {
+                    info!("clearing drv8323 faults before clearing axis fault");
+                    DRV_CLEAR_SIGNAL.signal(());
+                    Err(())
+                }
+                // 保护条件未恢复时不能清除故障
+                AxisRequest::ClearFault => protection
+                    .clear(&power_readings(), DRV_FAULTS.load(Ordering::Relaxed))
+                    .map_err(|reason| warn!("fault not cleared: {:?}", reason))
+                    .and_then(|_| {
+                        axis.request(request)
+                            .map_err(|e| warn!("axis request rejected: {:?}", e))
+                    }),
+                _ => axis
+                    .request(request)
+                    .map_err(|e| warn!("axis request rejected: {:?}", e)),
+            };
+            if result.is_ok() {
+                apply_axis_state(&mut motor, &axis);
+            }
+        }
+        if axis.state() == AxisState::Calibrating {
+            // 等待栅极驱动使能
+            Timer::after_millis(10).await;
+            let ok = calibrate(&mut motor, &mut current_sense, &mut protection).await;
+            axis.calibration_finished(ok);
+            match protection.trip_reason() {
+                Some(reason) => trip(&mut motor, &mut axis, reason),
+                None => apply_axis_state(&mut motor, &axis),
+            }
+            // 校准期间的时序不计入统计
+            CURRENT_SAMPLE_SIGNAL.reset();
+            last_start = None;
+            last_tick = CONTROL_TICKS.load(Ordering::Relaxed);
+            continue;
+        }
+
+        let power = power_readings();
+        let currents = current_sense.get_phase_currents();
+        motor.set_bus_voltage(power.vbus);
+        motor.update_phase_current(currents);
+
+        let drv_faults = DRV_FAULTS.load(Ordering::Relaxed);
+        match protection.update(&power, &currents, drv_faults, dt) {
+            ProtectionState::Tripped(reason) => trip(&mut motor, &mut axis, reason),
+            ProtectionState::Derated(scale) => motor.set_derating(scale),
+            ProtectionState::Normal => motor.set_derating(1.0),
+        }
+        motor.step(axis.target()).await;
+
+        timing
+            .execution
+            .record(DWT::cycle_count().wrapping_sub(start));
+        if timing.execution.count >= CONTROL_FREQUENCY_HZ {
+            LOOP_TIMING.lock(|t| t.set(timing));
+            MOTOR_PROFILE.lock(|p| p.set(motor.profile));
+            motor.profile.reset();
+            debug!(
+                "loop: period {}us (max {}us), execution {}us (max {}us), overruns {}",
+                cycles_to_us(timing.period.average()),
+                cycles_to_us(timing.period.max),
+                cycles_to_us(timing.execution.average()),
+                cycles_to_us(timing.execution.max),
+                timing.overruns
+            );
+            timing.reset();
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use core::sync::atomic::Ordering;
+use defmt::*;
+use embassy_futures::select::{select4, Either4};
+use embassy_time::{Duration, Instant, Timer};
+
+use super::messages::{
+    axis_status, Events, AXIS_REQUEST_CHANNEL, DRV_CLEAR_SIGNAL, DRV_FAULTS, DRV_GATE_SIGNAL,
+    EVENT_CHANNEL,
+};
+use crate::{
+    drivers::tim1,
+    hws::{
+        drv8323rs::{report_faults, FaultStatus},
+        nfault::NFault,
+    },
+    resources::Drv8323,
+};
+use caw_foc_core::axis::AxisRequest;
+
+/// 故障后的恢复策略
+#[derive(Debug, Format, Clone, Copy, PartialEq)]
+pub enum FaultPolicy {
+    /// 保持输出关闭, 直到ClearFault请求清除故障
+    Latch,
+    /// 清除故障后重试, 每次重试的等待时间加倍;
+    /// 恢复后hold_off_ms内没有再次故障时重试次数清零, 重试次数用完后与Latch相同
+    AutoRetry {
+        max_retries: u8,
+        backoff_ms: u32,
+        hold_off_ms: u32,
+    },
+}
+
+/// 清除后更新DRV_FAULTS并上报
+fn faults_cleared() {
+    DRV_FAULTS.store(0, Ordering::Relaxed);
+    if EVENT_CHANNEL.try_send(Events::DrvFaultCleared).is_err() {
+        warn!("event channel full, drv8323 fault cleared event dropped");
+    }
+}
+
+/// 读取并上报nFAULT故障, 按策略恢复
+///
+/// PWM输出已在nFAULT中断中关闭, 这里再关闭一次以覆盖任务启动时nFAULT已为低的情况。
+/// 同时处理DRV_GATE_SIGNAL的栅极驱动使能请求, DRV8323只由该任务访问。
+/// 故障锁存(Latch或重试次数用完)后不再等待nFAULT, 但继续处理栅极请求,
+/// 并在收到DRV_CLEAR_SIGNAL时清除故障, 成功后重新发送ClearFault让状态机退出Fault。
+#[embassy_executor::task]
+pub async fn drv8323_fault_task(mut drv: Drv8323, mut nfault: NFault, policy: FaultPolicy) {
+    let mut retries = 0u8;
+    let mut cleared_at = Instant::now();
+    let mut latched = false;
+    loop {
+        // 恢复后持续无故障的时间到达时清零重试次数
+        let reset_at = match policy {
+            FaultPolicy::AutoRetry { hold_off_ms, .. } if retries > 0 && !latched => {
+                Some(cleared_at + Duration::from_millis(hold_off_ms as u64))
+            }
+            _ => None,
+        };
+        let hold_off = async move {
+            match reset_at {
+                Some(at) => Timer::at(at).await,
+                None => core::future::pending::<()>().await,
+            }
+        };
+        // 锁存后nFAULT保持为低, 不再等待以免反复进入故障处理
+        let fault = async {
+            if latched {
+                core::future::pending::<()>().await
+            } else {
+                nfault.wait_for_low().await
+            }
+        };
+        match select4(
+            fault,
+            DRV_GATE_SIGNAL.wait(),
+            hold_off,
+            DRV_CLEAR_SIGNAL.wait(),
+        )
+        .await
+        {
+            Either4::First(()) => {}
+            Either4::Second(enable) => {
+                if enable && latched {
+                    warn!("drv8323 fault latched, gate driver stays in coast");
+                    continue;
+                }
+                let result = if enable {
+                    drv.enable_gd().await
+                } else {
+                    drv.disable_gd().await
+                };
+                match result {
+                    Ok(()) => debug!("drv8323 gate driver enabled: {}", enable),
+                    Err(e) => error!("drv8323 gate driver switch failed: {:?}", e),
+                }
+                continue;
+            }
+            Either4::Third(()) => {
+                debug!("drv8323 no fault since last recovery, retries reset");
+                retries = 0;
+                continue;
+            }
+            Either4::Fourth(()) => {
+                if let Err(e) = drv.clear_faults().await {
+                    error!("drv8323 clear faults failed: {:?}", e);
+                    continue;
+                }
+                if nfault.is_low() {
+                    warn!("drv8323 fault still present, not cleared");
+                    continue;
+                }
+                info!("drv8323 fault cleared by request");
+                latched = false;
+                retries = 0;
+                faults_cleared();
+                if AXIS_REQUEST_CHANNEL
+                    .try_send(AxisRequest::ClearFault)
+                    .is_err()
+                {
+                    warn!("axis request channel full, clear fault request dropped");
+                }
+                continue;
+            }
+        }
+        tim1::force_outputs_off();
+
+        let faults = drv.read_faults().await.unwrap_or_else(|e| {
+            // 无法读取故障寄存器时仍按故障处理, 保证保护管理器跳闸
+            error!("drv8323 read faults failed: {:?}", e);
+            FaultStatus {
+                fault: true,
+                ..Default::default()
+            }
+        });
+        let bits = faults.bits();
+        error!("drv8323 fault: {:?}", faults);
+        DRV_FAULTS.store(bits, Ordering::Relaxed);
+        if EVENT_CHANNEL.try_send(Events::DrvFault(bits)).is_err() {
+            warn!("event channel full, drv8323 fault event dropped");
+        }
+        report_faults(&faults);
+
+        match policy {
+            FaultPolicy::Latch => {
+                error!("drv8323 fault latched, outputs stay off until cleared");
+                latched = true;
+            }
+            FaultPolicy::AutoRetry {
+                max_retries,
+                backoff_ms,
+                ..
+            } => {
+                if retries >= max_retries {
+                    error!(
+                        "drv8323 fault retries exhausted ({}), outputs stay off until cleared",
+                        retries
+                    );
+                    latched = true;
+                    continue;
+                }
+                Timer::after_millis((backoff_ms as u64) << retries).await;
+                retries += 1;
+                if let Err(e) = drv.clear_faults().await {
+                    error!("drv8323 clear faults failed: {:?}", e);
+                    continue;
+                }
+                // 故障仍然存在时重新进入故障处理
+                if nfault.is_low() {
+                    continue;
+                }
+                info!("drv8323 fault cleared, retry {}/{}", retries, max_retries);
+                cleared_at = Instant::now();
+                // 状态机已离开需要输出的状态(如保护跳闸进入Fault)时保持MOE关闭,
+                // 由apply_axis_state在重新使能输出时恢复
+                if axis_status().state.output_enabled() {
+                    tim1::enable_outputs();
+                }
+                faults_cleared();
+            }
+        }
+    }
+}
@@ -1,17 +1,110 @@
 use defmt::Format;
+
+use caw_foc_core::{
+    axis::{AxisRequest, AxisState, AxisStatus},
+    config::ConfigRequest,
+    monitor::Readings,
+    motor::ControlType,
+    protection::TripReason,
+    timing::{LoopTiming, Profile, ProfileReport},
+};
+use core::{cell::Cell, sync::atomic::AtomicU32};
 use embassy_sync::{
-    blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, signal::Signal,
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    channel::Channel,
+    signal::Signal,
 };
 
 #[derive(PartialEq, Debug, Format)]
-pub enum Events {}
+pub enum Events {
+    /// nFAULT拉低, 携带锁存的故障位域
+    DrvFault(u32),
+    /// 故障已清除, PWM输出恢复
+    DrvFaultCleared,
+    /// 保护管理器跳闸, 电机输出已关闭
+    ProtectionTrip(TripReason),
+}
 
 #[derive(PartialEq, Debug, Format)]
 pub enum Commands {
     UsartTxBytes(&'static [u8]),
     UsartTxStr(&'static str),
+    /// DRV8323故障位域, 见FaultStatus::bits
+    UsartTxFaults(u32),
+    CanTxFaults(u32),
+    /// 状态机状态变化
+    UsartTxAxisStatus(AxisStatus),
+    /// 性能统计, 响应USART的PROF命令
+    UsartTxProfile(ProfileReport),
 }
 
 pub static EVENT_CHANNEL: Channel<CriticalSectionRawMutex, Events, 10> = Channel::new();
 
 pub static USART_WRITE_SIGNAL: Signal<CriticalSectionRawMutex, Commands> = Signal::new();
+
+pub static CAN_WRITE_SIGNAL: Signal<CriticalSectionRawMutex, Commands> = Signal::new();
+
+/// CAN/USART收到的状态切换请求, 由控制循环处理
+pub static AXIS_REQUEST_CHANNEL: Channel<CriticalSectionRawMutex, AxisRequest, 4> = Channel::new();
+
+/// 栅极驱动使能请求, true为enable_gd, false为disable_gd
+pub static DRV_GATE_SIGNAL: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+/// ClearFault时DRV8323仍有锁存的故障, 请求drv8323_fault_task清除, 成功后由其重新发送ClearFault
+pub static DRV_CLEAR_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// CAN/USART和校准产生的配置修改请求, 由config_task处理
+pub static CONFIG_REQUEST_CHANNEL: Channel<CriticalSectionRawMutex, ConfigRequest, 4> =
+    Channel::new();
+
+/// 最近一次锁存的DRV8323故障位域, 0表示无故障
+pub static DRV_FAULTS: AtomicU32 = AtomicU32::new(0);
+
+/// 最近一次滤波后的母线电压和温度
+pub static POWER_READINGS: Mutex<CriticalSectionRawMutex, Cell<Readings>> =
+    Mutex::new(Cell::new(Readings {
+        vbus: 0.0,
+        fet_temperature: 0.0,
+        mcu_temperature: 0.0,
+        ntc_fault: false,
+    }));
+
+pub fn power_readings() -> Readings {
+    POWER_READINGS.lock(|r| r.get())
+}
+
+/// 控制循环中状态机的最新状态
+pub static AXIS_STATUS: Mutex<CriticalSectionRawMutex, Cell<AxisStatus>> =
+    Mutex::new(Cell::new(AxisStatus {
+        state: AxisState::Idle,
+        control_type: ControlType::None,
+        fault: None,
+    }));
+
+pub fn axis_status() -> AxisStatus {
+    AXIS_STATUS.lock(|s| s.get())
+}
+
+/// 控制循环最近一个统计窗口的实测时序
+pub static LOOP_TIMING: Mutex<CriticalSectionRawMutex, Cell<LoopTiming>> =
+    Mutex::new(Cell::new(LoopTiming::new()));
+
+pub fn loop_timing() -> LoopTiming {
+    LOOP_TIMING.lock(|t| t.get())
+}
+
+/// 控制循环中Motor各段代码最近一个统计窗口的执行时间
+pub static MOTOR_PROFILE: Mutex<CriticalSectionRawMutex, Cell<Profile>> =
+    Mutex::new(Cell::new(Profile::new()));
+
+pub fn motor_profile() -> Profile {
+    MOTOR_PROFILE.lock(|p| p.get())
+}
+
+/// profiling_task每秒汇总的性能统计
+pub static PROFILE_REPORT: Mutex<CriticalSectionRawMutex, Cell<ProfileReport>> =
+    Mutex::new(Cell::new(ProfileReport::new()));
+
+pub fn profile_report() -> ProfileReport {
+    PROFILE_REPORT.lock(|r| r.get())
+}
@@ -1,4 +1,9 @@
 pub mod can;
+pub mod config;
+pub mod control;
+pub mod drv8323;
 pub mod messages;
+pub mod monitor;
+pub mod profiling;
 pub mod state;
 pub mod usart;
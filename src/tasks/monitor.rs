@@ -0,0 +1,30 @@
+use defmt::*;
+use embassy_time::{Duration, Ticker};
+
+use super::messages::POWER_READINGS;
+use crate::hws::power_monitor::{AdcMonitor, MONITOR_CONFIG};
+use caw_foc_core::monitor::PowerMonitor;
+
+/// 采样周期(ms)
+const SAMPLE_PERIOD_MS: u64 = 1;
+/// 日志输出间隔, 以采样次数计
+const LOG_INTERVAL: u32 = 1000;
+
+/// 按固定周期采样母线电压和温度, 滤波后写入POWER_READINGS
+#[embassy_executor::task]
+pub async fn power_monitor_task(mut adc: AdcMonitor) {
+    let mut monitor = PowerMonitor::new(MONITOR_CONFIG, SAMPLE_PERIOD_MS as f32 * 1e-3);
+    let mut ticker = Ticker::every(Duration::from_millis(SAMPLE_PERIOD_MS));
+    let mut count = 0u32;
+    loop {
+        let s = adc.sample().await;
+        let readings = monitor.update(s.vbus_voltage, s.ntc_voltage, s.mcu_temperature);
+        POWER_READINGS.lock(|r| r.set(readings));
+
+        count += 1;
+        if count % LOG_INTERVAL == 0 {
+            debug!("power: {:?}", readings);
+        }
+        ticker.next().await;
+    }
+}
@@ -0,0 +1,66 @@
+use core::sync::atomic::Ordering;
+use cortex_m::peripheral::DWT;
+use defmt::*;
+use embassy_time::{Duration, Ticker};
+
+use super::{
+    control::cycles_to_us,
+    messages::{loop_timing, motor_profile, PROFILE_REPORT},
+};
+use crate::executor::IDLE_CYCLES;
+use caw_foc_core::{
+    fast_math::{defines::_2PI, math::fast_sincos},
+    timing::{cpu_load, measure, ProfileReport, TimingStats},
+};
+
+/// 统计窗口(ms)
+const REPORT_PERIOD_MS: u64 = 1000;
+/// 每个窗口测量fast_sincos的次数, 角度均匀分布在一个周期内
+const SINCOS_SAMPLES: u32 = 64;
+
+/// 测量fast_sincos的单次执行时间
+fn profile_sincos() -> TimingStats {
+    let mut stats = TimingStats::new();
+    for i in 0..SINCOS_SAMPLES {
+        let angle = i as f32 * _2PI / SINCOS_SAMPLES as f32;
+        core::hint::black_box(measure(DWT::cycle_count, &mut stats, || {
+            fast_sincos(core::hint::black_box(angle))
+        }));
+    }
+    stats
+}
+
+/// 每秒汇总CPU负载、控制循环时序和各段代码的执行时间, 写入PROFILE_REPORT并输出日志
+///
+/// CPU负载由线程模式执行器的空闲时间计算, 包括中断执行器中的控制循环。
+#[embassy_executor::task]
+pub async fn profiling_task() {
+    let mut ticker = Ticker::every(Duration::from_millis(REPORT_PERIOD_MS));
+    IDLE_CYCLES.store(0, Ordering::Relaxed);
+    let mut last = DWT::cycle_count();
+    loop {
+        ticker.next().await;
+        let now = DWT::cycle_count();
+        let idle = IDLE_CYCLES.swap(0, Ordering::Relaxed);
+        let report = ProfileReport {
+            cpu_load: cpu_load(idle, now.wrapping_sub(last)),
+            loop_timing: loop_timing(),
+            motor: motor_profile(),
+            sincos: profile_sincos(),
+        };
+        last = now;
+        PROFILE_REPORT.lock(|r| r.set(report));
+
+        let m = &report.motor;
+        debug!(
+            "profile: load {}%, step {}us (max {}us), set_phase_voltage {}us (max {}us), sincos {}us (max {}us)",
+            report.cpu_load * 100.0,
+            cycles_to_us(m.step.average()),
+            cycles_to_us(m.step.max),
+            cycles_to_us(m.set_phase_voltage.average()),
+            cycles_to_us(m.set_phase_voltage.max),
+            cycles_to_us(report.sincos.average()),
+            cycles_to_us(report.sincos.max)
+        );
+    }
+}
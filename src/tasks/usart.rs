@@ -1,4 +1,4 @@
-use defmt::info;
+use defmt::{info, warn};
 use embassy_executor::Spawner;
 
 use embassy_stm32::{
@@ -7,10 +7,20 @@ use embassy_stm32::{
     peripherals,
     usart::{self, Config, UartTx},
 };
+use heapless::String;
 
 use crate::Usart1Resources;
+use caw_foc_core::comm::{
+    parse_axis_command, parse_config_command, write_fault_line, write_profile_line,
+    write_status_line,
+};
+
+use super::messages::{
+    profile_report, Commands, AXIS_REQUEST_CHANNEL, CONFIG_REQUEST_CHANNEL, USART_WRITE_SIGNAL,
+};
 
-use super::messages::{Commands, USART_WRITE_SIGNAL};
+/// 命令行的最大长度
+const LINE_LEN: usize = 48;
 
 bind_interrupts!(struct Irqs {
     USART1 => usart::InterruptHandler<peripherals::USART1>;
@@ -28,6 +38,22 @@ pub async fn usart1_write_task(mut tx: UartTx<'static, Async>) {
                 info!("{:?}", str);
                 tx.write(str.as_bytes()).await.unwrap();
             }
+            Commands::UsartTxFaults(bits) => {
+                let mut line: String<32> = String::new();
+                write_fault_line(&mut line, bits).unwrap();
+                tx.write(line.as_bytes()).await.unwrap();
+            }
+            Commands::UsartTxAxisStatus(status) => {
+                let mut line: String<48> = String::new();
+                write_status_line(&mut line, &status).unwrap();
+                tx.write(line.as_bytes()).await.unwrap();
+            }
+            Commands::UsartTxProfile(report) => {
+                let mut line: String<128> = String::new();
+                write_profile_line(&mut line, &report).unwrap();
+                tx.write(line.as_bytes()).await.unwrap();
+            }
+            _ => {}
         }
     }
 }
@@ -42,8 +68,39 @@ pub async fn usart1_task(spawner: Spawner, r: Usart1Resources) {
     .unwrap();
     let (tx, mut rx) = usart.split();
     spawner.spawn(usart1_write_task(tx)).unwrap();
+    let mut line: String<LINE_LEN> = String::new();
     loop {
         let mut buf = [0; 1];
         rx.read(&mut buf[..]).await.unwrap();
+        match buf[0] {
+            b'\r' | b'\n' => {
+                if line.is_empty() {
+                    continue;
+                }
+                if line.trim() == "PROF" {
+                    USART_WRITE_SIGNAL.signal(Commands::UsartTxProfile(profile_report()));
+                    line.clear();
+                    continue;
+                }
+                if let Some(request) = parse_axis_command(&line) {
+                    if AXIS_REQUEST_CHANNEL.try_send(request).is_err() {
+                        warn!("axis request channel full, {:?} dropped", request);
+                    }
+                } else if let Some(request) = parse_config_command(&line) {
+                    if CONFIG_REQUEST_CHANNEL.try_send(request).is_err() {
+                        warn!("config request channel full, {:?} dropped", request);
+                    }
+                } else {
+                    USART_WRITE_SIGNAL.signal(Commands::UsartTxStr("ERR\r\n"));
+                }
+                line.clear();
+            }
+            c => {
+                // 超长或非ASCII的行整行丢弃
+                if !c.is_ascii() || line.push(c as char).is_err() {
+                    line.clear();
+                }
+            }
+        }
     }
 }